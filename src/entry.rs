@@ -0,0 +1,311 @@
+use crate::{Index, IndexMap, Item};
+use generic_btree::{Address, Insert, StorageMut};
+use std::fmt;
+
+/// Entry in a map, for in-place manipulation.
+///
+/// Obtained with [`IndexMap::entry`].
+pub enum Entry<'a, K, V> {
+	Occupied(OccupiedEntry<'a, K, V>),
+	Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+	/// Returns the index of the entry,
+	/// either the index of the existing binding or the index it would be inserted at.
+	#[inline]
+	pub fn index(&self) -> usize {
+		match self {
+			Self::Occupied(entry) => entry.index(),
+			Self::Vacant(entry) => entry.index(),
+		}
+	}
+
+	/// Returns the key of the entry.
+	#[inline]
+	pub fn key(&self) -> &K {
+		match self {
+			Self::Occupied(entry) => entry.key(),
+			Self::Vacant(entry) => entry.key(),
+		}
+	}
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+	/// Inserts the given default value if the entry is vacant,
+	/// and returns a mutable reference to the value.
+	#[inline]
+	pub fn or_insert(self, default: V) -> &'a mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Vacant(entry) => entry.insert(default),
+		}
+	}
+
+	/// Inserts the result of the `default` function if the entry is vacant,
+	/// and returns a mutable reference to the value.
+	#[inline]
+	pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Vacant(entry) => entry.insert(default()),
+		}
+	}
+
+	/// Inserts the result of the `default` function, called with the key,
+	/// if the entry is vacant, and returns a mutable reference to the value.
+	#[inline]
+	pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'a mut V {
+		match self {
+			Self::Occupied(entry) => entry.into_mut(),
+			Self::Vacant(entry) => {
+				let value = default(entry.key());
+				entry.insert(value)
+			}
+		}
+	}
+
+	/// Inserts the default value if the entry is vacant,
+	/// and returns a mutable reference to the value.
+	#[inline]
+	pub fn or_default(self) -> &'a mut V
+	where
+		V: Default,
+	{
+		self.or_insert_with(V::default)
+	}
+
+	/// Modifies the value in place if the entry is occupied.
+	#[inline]
+	pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+		if let Self::Occupied(entry) = &mut self {
+			f(entry.get_mut())
+		}
+
+		self
+	}
+}
+
+/// Occupied entry.
+pub struct OccupiedEntry<'a, K, V> {
+	map: &'a mut IndexMap<K, V>,
+
+	/// Address of the item in the B-Tree.
+	address: Address,
+
+	/// Index of the item in the inner list.
+	index: Index,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+	#[inline]
+	pub(crate) fn new(map: &'a mut IndexMap<K, V>, address: Address) -> Self {
+		let index = map.nodes[address.id]
+			.item(address.offset.value().unwrap())
+			.unwrap();
+		Self {
+			map,
+			address,
+			index,
+		}
+	}
+
+	/// Returns the index of the binding in the map.
+	#[inline]
+	pub fn index(&self) -> usize {
+		self.map.inner.items.get(self.index).unwrap().order
+	}
+
+	/// Returns the key stored in the map.
+	#[inline]
+	pub fn key(&self) -> &K {
+		self.map.inner.items.get(self.index).unwrap().as_key()
+	}
+
+	#[inline]
+	pub fn get(&self) -> &V {
+		self.map.inner.items.get(self.index).unwrap().as_value()
+	}
+
+	#[inline]
+	pub fn get_mut(&mut self) -> &mut V {
+		self.map
+			.inner
+			.items
+			.get_mut(self.index)
+			.unwrap()
+			.as_value_mut()
+	}
+
+	/// Converts the entry into a mutable reference to its value.
+	#[inline]
+	pub fn into_mut(self) -> &'a mut V {
+		self.map
+			.inner
+			.items
+			.get_mut(self.index)
+			.unwrap()
+			.as_value_mut()
+	}
+
+	/// Replaces the value of the entry and returns the old value.
+	#[inline]
+	pub fn insert(&mut self, value: V) -> V {
+		self.map
+			.inner
+			.items
+			.get_mut(self.index)
+			.unwrap()
+			.replace_value(value)
+	}
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+	/// Removes the item from the B-Tree at the address of the entry,
+	/// without searching the tree a second time.
+	/// The item itself is left in the inner list.
+	#[inline]
+	fn remove_from_btree(&mut self) {
+		let removed = self.map.btree_mut().remove_at(self.address);
+		debug_assert_eq!(removed.map(|(index, _)| index), Some(self.index))
+	}
+
+	/// Remove the binding and returns its value.
+	///
+	/// The last binding of the map takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn swap_remove(self) -> V {
+		self.swap_remove_entry().1
+	}
+
+	/// Remove the binding and returns its value,
+	/// preserving the order of the remaining bindings.
+	///
+	/// Computes in **O(n)** time (average).
+	#[inline]
+	pub fn shift_remove(self) -> V {
+		self.shift_remove_entry().1
+	}
+
+	/// Remove the binding and returns its key and value.
+	///
+	/// This is an alias to [`Self::swap_remove_entry`],
+	/// meaning that this function *disturbs the order of the map*.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn remove_entry(self) -> (K, V) {
+		self.swap_remove_entry()
+	}
+
+	/// Remove the binding and returns its key and value.
+	///
+	/// The last binding of the map takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn swap_remove_entry(self) -> (K, V) {
+		self.remove_from_btree();
+		let item = self.map.inner.swap_remove(self.index);
+		(item.key, item.value)
+	}
+
+	/// Remove the binding and returns its key and value,
+	/// preserving the order of the remaining bindings.
+	///
+	/// Computes in **O(n)** time (average).
+	#[inline]
+	pub fn shift_remove_entry(self) -> (K, V) {
+		self.remove_from_btree();
+		let item = self.map.inner.shift_remove(self.index);
+		(item.key, item.value)
+	}
+}
+
+/// Vacant entry.
+pub struct VacantEntry<'a, K, V> {
+	map: &'a mut IndexMap<K, V>,
+
+	key: K,
+
+	/// Address in the B-Tree where the key must be inserted.
+	address: Address,
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+	#[inline]
+	pub(crate) fn new(map: &'a mut IndexMap<K, V>, key: K, address: Address) -> Self {
+		Self { map, key, address }
+	}
+
+	/// Returns the index the binding will be inserted at.
+	#[inline]
+	pub fn index(&self) -> usize {
+		self.map.len()
+	}
+
+	#[inline]
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	#[inline]
+	pub fn into_key(self) -> K {
+		self.key
+	}
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+	/// Inserts the binding, and returns a mutable reference to the value.
+	///
+	/// The item is inserted at the address found by [`IndexMap::entry`],
+	/// without searching the tree a second time.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn insert(self, value: V) -> &'a mut V {
+		let map = self.map;
+		let mut btree = map.btree_mut();
+		let index = btree.allocate_item(Item::new(self.key, value));
+		btree.insert_at(self.address, index);
+		drop(btree);
+		map.inner.items.get_mut(index).unwrap().as_value_mut()
+	}
+}
+
+/// Error returned by [`IndexMap::try_insert`] when the key is already in the map.
+///
+/// Contains the occupied entry, and the value that was not inserted.
+pub struct OccupiedError<'a, K, V> {
+	/// The entry in the map that was already occupied.
+	pub entry: OccupiedEntry<'a, K, V>,
+
+	/// The value which was not inserted, because the entry was already occupied.
+	pub value: V,
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug> fmt::Debug for OccupiedError<'a, K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("OccupiedError")
+			.field("key", self.entry.key())
+			.field("old_value", self.entry.get())
+			.field("new_value", &self.value)
+			.finish()
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug> fmt::Display for OccupiedError<'a, K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"failed to insert {:?}, key {:?} already exists with value {:?}",
+			self.value,
+			self.entry.key(),
+			self.entry.get(),
+		)
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug> std::error::Error for OccupiedError<'a, K, V> {}
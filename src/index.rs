@@ -6,3 +6,9 @@ pub use reference_mut::Mut;
 
 /// Item index.
 pub type Index = usize;
+
+/// Key of an item stored in the collection.
+///
+/// Searching the B-Tree with this key finds the node holding the item
+/// without borrowing the item's actual key.
+pub struct ItemKey(pub Index);
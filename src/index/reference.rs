@@ -23,6 +23,11 @@ impl<'a, K, V> Ref<'a, K, V> {
 	pub fn item(&self) -> &item::Ordered<K, V> {
 		self.inner.items.get(self.index).unwrap()
 	}
+
+	/// Returns a reference to another item of the same collection.
+	pub fn with_index(&self, index: Index) -> Self {
+		Self::new(index, self.inner)
+	}
 }
 
 impl<'a, 'b, K1, V1, K2, V2> PartialEq<Ref<'b, K2, V2>> for Ref<'a, K1, V1>
@@ -1,5 +1,5 @@
 use super::Index;
-use crate::{Inner, Item};
+use crate::{item, Inner, Item};
 
 pub struct Mut<'a, K, V> {
 	index: Index,
@@ -15,10 +15,6 @@ impl<'a, K, V> Mut<'a, K, V> {
 	// 	self.index
 	// }
 
-	pub fn into_index(self) -> Index {
-		self.index
-	}
-
 	// pub fn item_mut(&mut self) -> &mut item::Ordered<K, V> {
 	// 	self.inner.items.get_mut(self.index).unwrap()
 	// }
@@ -50,3 +46,14 @@ impl<'a, K, V> generic_btree::node::item::Replace<crate::Mut<'a, K, V>, Item<K,
 		)
 	}
 }
+
+impl<'a, K, V> generic_btree::node::item::Replace<crate::Mut<'a, K, V>, item::Unique<K, V>>
+	for Mut<'a, K, V>
+{
+	/// The existing item index, and the rejected item.
+	type Output = (Index, Item<K, V>);
+
+	fn replace(&mut self, item: item::Unique<K, V>) -> Self::Output {
+		(self.index, item.0)
+	}
+}
@@ -19,6 +19,13 @@ impl<K, V> Item<K, V> {
 	}
 }
 
+/// Item that must not replace an existing item with the same key.
+///
+/// Inserting it in the B-Tree gives it back if the key is already present,
+/// instead of replacing the value.
+pub struct Unique<K, V>(pub Item<K, V>);
+
+#[derive(Clone)]
 pub struct Ordered<K, V> {
 	pub key: K,
 	pub value: V,
@@ -46,6 +53,14 @@ impl<K, V> Ordered<K, V> {
 		}
 	}
 
+	pub fn as_full(&self) -> (usize, &K, &V) {
+		(self.order, &self.key, &self.value)
+	}
+
+	pub fn as_full_mut(&mut self) -> (usize, &K, &mut V) {
+		(self.order, &self.key, &mut self.value)
+	}
+
 	pub fn as_key(&self) -> &K {
 		&self.key
 	}
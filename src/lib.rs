@@ -1,20 +1,35 @@
 #![feature(generic_associated_types)]
+#![feature(try_reserve_kind)]
 use generic_btree::{Storage, StorageMut};
-use slab::Slab;
-use slab_lists::SlabList;
-use std::{borrow::Borrow, fmt};
-
+use slots::{Slots, SlotsMut};
+use std::{
+	borrow::Borrow,
+	cmp::Ordering,
+	collections::{TryReserveError, TryReserveErrorKind},
+	convert::Infallible,
+	fmt,
+	hash::{Hash, Hasher},
+	ops::{Bound, RangeBounds},
+};
+
+mod entry;
 mod index;
 mod item;
 mod node;
 mod reference;
 mod reference_mut;
+pub mod set;
+mod slots;
+mod sorted;
 
+pub use entry::{Entry, OccupiedEntry, OccupiedError, VacantEntry};
 pub(crate) use index::Index;
 pub(crate) use item::Item;
 pub(crate) use node::Node;
 pub(crate) use reference::Ref;
 pub(crate) use reference_mut::Mut;
+pub use set::IndexSet;
+pub use sorted::{Range, RangeMut, SortedIter, SortedKeys};
 
 /// Calculates the quotient of `a` and `b`, rounding the result towards positive infinity.
 ///
@@ -29,6 +44,46 @@ const fn div_ceil(a: usize, b: usize) -> usize {
 	}
 }
 
+/// Returns the error reported when a capacity overflows `usize`.
+fn capacity_overflow() -> TryReserveError {
+	TryReserveErrorKind::CapacityOverflow.into()
+}
+
+/// Converts the given range of indexes into a `start..end` range,
+/// checking that it is included in `0..len`.
+///
+/// ## Panics
+///
+/// Panics if the start of the range is greater than its end,
+/// or if the end of the range is greater than `len`.
+fn index_range<R: RangeBounds<usize>>(range: R, len: usize) -> std::ops::Range<usize> {
+	let start = match range.start_bound() {
+		Bound::Included(&start) => start,
+		Bound::Excluded(&start) => start.checked_add(1).expect("range start overflow"),
+		Bound::Unbounded => 0,
+	};
+
+	let end = match range.end_bound() {
+		Bound::Included(&end) => end.checked_add(1).expect("range end overflow"),
+		Bound::Excluded(&end) => end,
+		Bound::Unbounded => len,
+	};
+
+	assert!(
+		start <= end,
+		"range starts at {} but ends at {}",
+		start,
+		end
+	);
+	assert!(
+		end <= len,
+		"range end {} out of bounds (len is {})",
+		end,
+		len
+	);
+	start..end
+}
+
 /// Knuth order of the B-Trees.
 ///
 /// Must be at least 4.
@@ -40,7 +95,7 @@ pub struct IndexMap<K, V> {
 	/// Note that the nodes does not actually store the items
 	/// of the collection, but only indexes referencing the items
 	/// in the inner linked list.
-	nodes: Slab<Node>,
+	nodes: Slots<Node>,
 
 	/// Inner data structure storing the actua items data
 	/// and ordering.
@@ -55,18 +110,19 @@ impl<K, V> IndexMap<K, V> {
 	#[inline]
 	pub fn new() -> Self {
 		Self {
-			nodes: Slab::new(),
+			nodes: Slots::new(),
 			inner: Inner::new(),
 			root: None,
 		}
 	}
 
 	/// Creates a new empty map with the given capacity.
+	///
+	/// See [`Self::capacity`] for the guarantees of the capacity.
 	#[inline]
 	pub fn with_capacity(capacity: usize) -> Self {
-		let nodes_capacity = div_ceil(capacity, M - 1);
 		Self {
-			nodes: Slab::with_capacity(nodes_capacity),
+			nodes: Slots::with_capacity(node::nodes_for(capacity)),
 			inner: Inner::with_capacity(capacity),
 			root: None,
 		}
@@ -84,27 +140,178 @@ impl<K, V> IndexMap<K, V> {
 		Mut::new(&mut self.nodes, &mut self.inner, &mut self.root)
 	}
 
-	/// Returns the current item capacity of the B-Tree.
+	/// Inserts the given bindings in the map, assuming it is empty.
+	///
+	/// Instead of inserting each binding in the B-Tree,
+	/// the bindings are sorted once and the B-Tree is built bottom-up.
+	/// Duplicate keys follow the semantics of [`Self::insert`]:
+	/// the first index is kept and the last value wins.
+	///
+	/// Computes in **O(n log n)** time.
+	fn build<I: Iterator<Item = (K, V)>>(&mut self, iter: I)
+	where
+		K: Ord,
+	{
+		debug_assert!(self.is_empty());
+		let mut bindings: Vec<Option<(K, V)>> = iter.map(Some).collect();
+
+		let mut sorted: Vec<usize> = (0..bindings.len()).collect();
+		sorted.sort_by(|&a, &b| {
+			bindings[a]
+				.as_ref()
+				.unwrap()
+				.0
+				.cmp(&bindings[b].as_ref().unwrap().0)
+		});
+
+		// Positions of the kept bindings, in key order.
+		let mut kept = Vec::with_capacity(sorted.len());
+		let mut i = 0;
+		while i < sorted.len() {
+			let first = sorted[i];
+			let mut j = i + 1;
+			while j < sorted.len()
+				&& bindings[sorted[j]].as_ref().unwrap().0 == bindings[first].as_ref().unwrap().0
+			{
+				j += 1
+			}
+
+			if j > i + 1 {
+				let (_, value) = bindings[sorted[j - 1]].take().unwrap();
+				for &duplicate in &sorted[(i + 1)..(j - 1)] {
+					bindings[duplicate] = None
+				}
+				bindings[first].as_mut().unwrap().1 = value;
+			}
+
+			kept.push(first);
+			i = j
+		}
+
+		let indexes: Vec<Option<Index>> = bindings
+			.into_iter()
+			.map(|binding| binding.map(|(key, value)| self.inner.insert(Item::new(key, value))))
+			.collect();
+
+		let sorted: Vec<Index> = kept.into_iter().map(|p| indexes[p].unwrap()).collect();
+		node::reserve(&mut self.nodes, self.inner.capacity());
+		self.root = node::build(&mut self.nodes, &sorted);
+	}
+
+	/// Returns an iterator over the items of the map, in key order.
+	#[inline]
+	fn sorted_items(&self) -> impl DoubleEndedIterator<Item = index::Ref<'_, K, V>> + '_ {
+		sorted::Indexes::new(self.btree()).map(move |i| index::Ref::new(i, &self.inner))
+	}
+
+	/// Returns the number of items the B-Tree nodes can hold without reallocating.
 	///
-	/// Every node contains at most M-1 items.
+	/// This is not the number of items the current nodes could store,
+	/// but the number of items any B-Tree built with as many nodes is guaranteed to hold,
+	/// whatever the insertion and removal history.
 	#[inline]
 	fn btree_capacity(&self) -> usize {
-		self.nodes.capacity() * (M - 1)
+		node::items_for(self.nodes.capacity())
 	}
 
+	/// Returns the number of bindings the map can hold without reallocating.
+	///
+	/// Inserting bindings in the map while its length does not exceed
+	/// its capacity is guaranteed to not perform any allocation.
+	/// This accounts for the items list, the index vector and the B-Tree nodes.
 	#[inline]
 	pub fn capacity(&self) -> usize {
 		std::cmp::min(self.inner.capacity(), self.btree_capacity())
 	}
 
+	/// Reserves capacity for at least `additional` more bindings.
+	///
+	/// ## Panics
+	///
+	/// Panics if the new capacity overflows `usize`.
+	#[inline]
+	pub fn reserve(&mut self, additional: usize) {
+		let capacity = self
+			.len()
+			.checked_add(additional)
+			.expect("capacity overflow");
+		self.inner.reserve(additional);
+		let capacity = std::cmp::max(capacity, self.inner.capacity());
+		node::reserve(&mut self.nodes, capacity)
+	}
+
+	/// Reserves capacity for exactly `additional` more bindings.
+	///
+	/// The capacity may still exceed the requested one,
+	/// since the B-Tree nodes are allocated for the worst case.
+	///
+	/// ## Panics
+	///
+	/// Panics if the new capacity overflows `usize`.
+	#[inline]
+	pub fn reserve_exact(&mut self, additional: usize) {
+		let capacity = self
+			.len()
+			.checked_add(additional)
+			.expect("capacity overflow");
+		self.inner.reserve_exact(additional);
+		node::reserve(&mut self.nodes, capacity)
+	}
+
+	/// Tries to reserve capacity for at least `additional` more bindings.
+	///
+	/// The index vector, the items list and the B-Tree nodes are all reserved fallibly.
+	/// An error is returned if the new capacity overflows `usize`
+	/// or if the allocator fails, and the bindings are then left untouched,
+	/// although some of the storages may have grown.
+	#[inline]
+	pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+		let capacity = self
+			.len()
+			.checked_add(additional)
+			.ok_or_else(capacity_overflow)?;
+		self.inner.try_reserve(additional)?;
+		let capacity = std::cmp::max(capacity, self.inner.capacity());
+		node::try_reserve(&mut self.nodes, capacity)
+	}
+
+	/// Shrinks the capacity of the map as much as possible.
+	///
+	/// See [`Self::shrink_to`] for the limits of shrinking.
+	#[inline]
+	pub fn shrink_to_fit(&mut self) {
+		self.shrink_to(0)
+	}
+
+	/// Shrinks the capacity of the map with a lower bound.
+	///
+	/// The capacity will remain at least as large as both the length
+	/// and the supplied value.
+	///
+	/// Items and B-Tree nodes keep their storage slot,
+	/// so that the indexes referencing them stay valid.
+	/// The slots freed by removals are not released: after removals,
+	/// the capacity does not shrink below the number of slots up to
+	/// the last remaining item, and may stay above the requested bound.
+	#[inline]
+	pub fn shrink_to(&mut self, min_capacity: usize) {
+		let capacity = std::cmp::max(self.len(), min_capacity);
+		self.inner.shrink_to(capacity);
+		if self.nodes.capacity() > node::nodes_for(capacity) {
+			self.nodes.shrink_to_fit()
+		}
+
+		node::reserve(&mut self.nodes, capacity)
+	}
+
 	#[inline]
 	pub fn len(&self) -> usize {
-		self.inner.items.len()
+		self.inner.vec.len()
 	}
 
 	#[inline]
 	pub fn is_empty(&self) -> bool {
-		self.inner.items.is_empty()
+		self.inner.vec.is_empty()
 	}
 
 	/// Get the index, key and value matching the given key.
@@ -120,8 +327,8 @@ impl<K, V> IndexMap<K, V> {
 		let btree = self.btree();
 		let index: Option<Index> = btree.get(key).map(index::Ref::into_index);
 		index.map(move |index| {
-			let (key, value) = self.inner.items.get(index).unwrap().as_pair();
-			(index, key, value)
+			let item = self.inner.items.get(index).unwrap();
+			(item.order, &item.key, &item.value)
 		})
 	}
 
@@ -154,8 +361,7 @@ impl<K, V> IndexMap<K, V> {
 		Q: Ord,
 		Self: 'a,
 	{
-		let mut btree = self.btree_mut();
-		let index: Option<Index> = btree.get_mut(key).map(index::Mut::into_index);
+		let index: Option<Index> = self.btree().get(key).map(index::Ref::into_index);
 		index
 			.map(move |index| self.inner.items.get_mut(index).unwrap())
 			.map(item::Ordered::as_value_mut)
@@ -188,6 +394,87 @@ impl<K, V> IndexMap<K, V> {
 			.map(|item| item.as_pair_mut())
 	}
 
+	/// Get the index of the given key.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn get_index_of<Q: ?Sized>(&self, key: &Q) -> Option<usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.get_full(key).map(|(index, _, _)| index)
+	}
+
+	/// Get the key and value matching the given key.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn get_key_value<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.get_full(key).map(|(_, key, value)| (key, value))
+	}
+
+	/// Checks if the map contains the given key.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.btree().get(key).is_some()
+	}
+
+	/// Get the index, key and mutable value matching the given key.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn get_full_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<(usize, &K, &mut V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let index: Option<Index> = self.btree().get(key).map(index::Ref::into_index);
+		index.map(move |index| self.inner.items.get_mut(index).unwrap().as_full_mut())
+	}
+
+	/// Get the first binding, by order of insertion.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn first(&self) -> Option<(&K, &V)> {
+		self.get_index(0)
+	}
+
+	/// Get the first binding, by order of insertion, with a mutable value.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn first_mut(&mut self) -> Option<(&K, &mut V)> {
+		self.get_index_mut(0)
+	}
+
+	/// Get the last binding, by order of insertion.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn last(&self) -> Option<(&K, &V)> {
+		self.get_index(self.len().checked_sub(1)?)
+	}
+
+	/// Get the last binding, by order of insertion, with a mutable value.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn last_mut(&mut self) -> Option<(&K, &mut V)> {
+		self.get_index_mut(self.len().checked_sub(1)?)
+	}
+
 	/// Inserts a key-value pair in the map and returns their index.
 	///
 	/// Computes in **O(log n)** time (average).
@@ -196,9 +483,10 @@ impl<K, V> IndexMap<K, V> {
 	where
 		K: Ord,
 	{
-		match self.btree_mut().insert(Item::new(key, value)) {
-			Some((index, value)) => (index, Some(value)),
-			None => (*self.inner.vec.last().unwrap(), None),
+		let replaced = self.btree_mut().insert(Item::new(key, value));
+		match replaced {
+			Some((index, value)) => (self.inner.items.get(index).unwrap().order, Some(value)),
+			None => (self.len() - 1, None),
 		}
 	}
 
@@ -215,6 +503,83 @@ impl<K, V> IndexMap<K, V> {
 			.map(|(_, value)| value)
 	}
 
+	/// Inserts a key-value pair in the map if the key is not already present,
+	/// and returns a mutable reference to the value.
+	///
+	/// If the key is already present, nothing is updated and an error containing
+	/// the occupied entry and the value is returned.
+	/// The B-Tree is searched once: the binding is inserted at the address
+	/// where the search ended.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<K, V>>
+	where
+		K: Ord,
+	{
+		match self.entry(key) {
+			Entry::Occupied(entry) => Err(OccupiedError { entry, value }),
+			Entry::Vacant(entry) => Ok(entry.insert(value)),
+		}
+	}
+
+	/// Builds a map from the given bindings, rejecting duplicate keys.
+	///
+	/// Bindings are inserted one by one, and the first duplicate key
+	/// is detected while inserting it in the B-Tree.
+	///
+	/// Computes in **O(n log n)** time.
+	pub fn try_from_iter<I>(iter: I) -> Result<Self, DuplicateKey<K>>
+	where
+		K: Ord,
+		I: IntoIterator<Item = (K, V)>,
+	{
+		let iter = iter.into_iter();
+		let mut map = Self::with_capacity(iter.size_hint().0);
+		for (key, value) in iter {
+			let rejected = map.btree_mut().insert(item::Unique(Item::new(key, value)));
+			if let Some((index, item)) = rejected {
+				return Err(DuplicateKey {
+					first_index: map.inner.items.get(index).unwrap().order,
+					second_index: map.len(),
+					key: item.key,
+				});
+			}
+		}
+
+		Ok(map)
+	}
+
+	/// Get the entry for the given key, for in-place manipulation.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn entry(&mut self, key: K) -> Entry<K, V>
+	where
+		K: Ord,
+	{
+		match self.btree().address_of(&key) {
+			Ok(address) => Entry::Occupied(OccupiedEntry::new(self, address)),
+			Err(address) => Entry::Vacant(VacantEntry::new(self, key, address)),
+		}
+	}
+
+	/// Removes the given item from the B-Tree.
+	///
+	/// The B-Tree is searched using the key of the item,
+	/// which must hence still be in the inner list.
+	/// The item itself is left in the inner list.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	fn btree_remove_item(&mut self, index: Index)
+	where
+		K: Ord,
+	{
+		let removed = self.btree_mut().remove(&index::ItemKey(index));
+		debug_assert_eq!(removed, Some(index))
+	}
+
 	/// Remove value.
 	///
 	/// This is an alias to [`Self::swap_remove`],
@@ -234,6 +599,8 @@ impl<K, V> IndexMap<K, V> {
 
 	/// Remove value.
 	///
+	/// The last binding of the map takes the place of the removed one.
+	///
 	/// Computes in **O(log n)** time (average).
 	#[inline]
 	pub fn swap_remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
@@ -241,10 +608,41 @@ impl<K, V> IndexMap<K, V> {
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match self.btree_mut().remove(key) {
+		self.swap_remove_full(key).map(|(_, _, value)| value)
+	}
+
+	/// Remove value, and returns it with its key.
+	///
+	/// The last binding of the map takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn swap_remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.swap_remove_full(key)
+			.map(|(_, key, value)| (key, value))
+	}
+
+	/// Remove value, and returns it with its index and key.
+	///
+	/// The last binding of the map takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn swap_remove_full<Q: ?Sized>(&mut self, key: &Q) -> Option<(usize, K, V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let removed = self.btree_mut().remove(key);
+		match removed {
 			Some(index) => {
+				let order = self.inner.items.get(index).unwrap().order;
 				let item = self.inner.swap_remove(index);
-				Some(item.value)
+				Some((order, item.key, item.value))
 			}
 			None => None,
 		}
@@ -252,6 +650,8 @@ impl<K, V> IndexMap<K, V> {
 
 	/// Remove value.
 	///
+	/// The order of the remaining bindings is preserved.
+	///
 	/// Computes in **O(n)** time (average).
 	#[inline]
 	pub fn shift_remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
@@ -259,774 +659,4093 @@ impl<K, V> IndexMap<K, V> {
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match self.btree_mut().remove(key) {
+		self.shift_remove_full(key).map(|(_, _, value)| value)
+	}
+
+	/// Remove value, and returns it with its key.
+	///
+	/// The order of the remaining bindings is preserved.
+	///
+	/// Computes in **O(n)** time (average).
+	#[inline]
+	pub fn shift_remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.shift_remove_full(key)
+			.map(|(_, key, value)| (key, value))
+	}
+
+	/// Remove value, and returns it with its index and key.
+	///
+	/// The order of the remaining bindings is preserved.
+	///
+	/// Computes in **O(n)** time (average).
+	#[inline]
+	pub fn shift_remove_full<Q: ?Sized>(&mut self, key: &Q) -> Option<(usize, K, V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let removed = self.btree_mut().remove(key);
+		match removed {
 			Some(index) => {
+				let order = self.inner.items.get(index).unwrap().order;
 				let item = self.inner.shift_remove(index);
-				Some(item.value)
+				Some((order, item.key, item.value))
 			}
 			None => None,
 		}
 	}
 
-	/// Returns an iterator over the bindings of the map.
+	/// Removes the last binding, by order of insertion, and returns it.
 	///
-	/// Bindings are iterated by order of insertion in the map.
+	/// Computes in **O(log n)** time (average).
 	#[inline]
-	pub fn iter(&self) -> Iter<K, V> {
-		Iter {
-			inner: &self.inner,
-			indexes: self.inner.vec.iter(),
-		}
+	pub fn pop(&mut self) -> Option<(K, V)>
+	where
+		K: Ord,
+	{
+		self.swap_remove_index(self.len().checked_sub(1)?)
 	}
-}
 
-impl<K, V> Default for IndexMap<K, V> {
-	fn default() -> Self {
-		Self::new()
+	/// Remove the binding at the given index, and returns its key and value.
+	///
+	/// The last binding of the map takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn swap_remove_index(&mut self, index: usize) -> Option<(K, V)>
+	where
+		K: Ord,
+	{
+		let i = *self.inner.vec.get(index)?;
+		self.btree_remove_item(i);
+		let item = self.inner.swap_remove(i);
+		Some((item.key, item.value))
 	}
-}
-
-impl<'a, K, V, Q> std::ops::Index<&'a Q> for IndexMap<K, V>
-where
-	K: Borrow<Q>,
-	Q: ?Sized + Ord,
-{
-	type Output = V;
 
-	fn index(&self, key: &'a Q) -> &V {
-		self.get(key).unwrap()
+	/// Remove the binding at the given index, and returns its key and value.
+	///
+	/// The order of the remaining bindings is preserved.
+	///
+	/// Computes in **O(n)** time (average).
+	#[inline]
+	pub fn shift_remove_index(&mut self, index: usize) -> Option<(K, V)>
+	where
+		K: Ord,
+	{
+		let i = *self.inner.vec.get(index)?;
+		self.btree_remove_item(i);
+		let item = self.inner.shift_remove(i);
+		Some((item.key, item.value))
 	}
-}
 
-impl<'a, K, V, Q> std::ops::IndexMut<&'a Q> for IndexMap<K, V>
-where
-	K: Borrow<Q>,
-	Q: ?Sized + Ord,
-{
-	fn index_mut(&mut self, key: &'a Q) -> &mut V {
-		self.get_mut(key).unwrap()
+	/// Returns the index, in the inner list, of the item with the smallest key.
+	#[inline]
+	fn first_item_index(&self) -> Option<Index> {
+		let btree = self.btree();
+		btree
+			.first_item_address()
+			.map(|address| btree.index_at(address.into()))
 	}
-}
 
-impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for IndexMap<K, V> {
+	/// Returns the index, in the inner list, of the item with the largest key.
 	#[inline]
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		f.debug_map().entries(self.iter()).finish()
+	fn last_item_index(&self) -> Option<Index> {
+		let btree = self.btree();
+		btree
+			.last_item_address()
+			.map(|address| btree.index_at(address.into()))
 	}
-}
 
-impl<'a, K, V> IntoIterator for &'a IndexMap<K, V> {
-	type Item = (&'a K, &'a V);
-	type IntoIter = Iter<'a, K, V>;
+	/// Removes the item at the given index from the B-Tree,
+	/// then removes it from the inner list with `remove`.
+	///
+	/// Returns the removed binding with its index.
+	#[inline]
+	fn pop_by_key(
+		&mut self,
+		index: Option<Index>,
+		remove: fn(&mut Inner<K, V>, Index) -> Item<K, V>,
+	) -> Option<(usize, K, V)>
+	where
+		K: Ord,
+	{
+		let index = index?;
+		let order = self.inner.items.get(index).unwrap().order;
+		self.btree_remove_item(index);
+		let item = remove(&mut self.inner, index);
+		Some((order, item.key, item.value))
+	}
 
+	/// Returns the binding with the smallest key, with its index.
+	///
+	/// Computes in **O(log n)** time.
 	#[inline]
-	fn into_iter(self) -> Self::IntoIter {
-		self.iter()
+	pub fn first_by_key(&self) -> Option<(usize, &K, &V)> {
+		self.first_item_index()
+			.map(|index| self.inner.items.get(index).unwrap().as_full())
 	}
-}
-
-impl<K, V> IntoIterator for IndexMap<K, V> {
-	type Item = (K, V);
-	type IntoIter = IntoIter<K, V>;
 
+	/// Returns the binding with the largest key, with its index.
+	///
+	/// Computes in **O(log n)** time.
 	#[inline]
-	fn into_iter(self) -> Self::IntoIter {
-		IntoIter {
-			indexes: self.inner.vec.into_iter(),
-			items: self.inner.items,
-		}
+	pub fn last_by_key(&self) -> Option<(usize, &K, &V)> {
+		self.last_item_index()
+			.map(|index| self.inner.items.get(index).unwrap().as_full())
 	}
-}
-
-pub(crate) struct Inner<K, V> {
-	/// Items.
-	items: SlabList<item::Ordered<K, V>>,
-
-	/// Ordering array.
-	vec: Vec<Index>,
-}
 
-impl<K, V> Inner<K, V> {
+	/// Removes the binding with the smallest key, and returns it with its index.
+	///
+	/// This is an alias to [`Self::swap_pop_first_by_key`],
+	/// meaning that this function *disturbs the order of the map*.
+	///
+	/// Computes in **O(log n)** time (average).
 	#[inline]
-	pub fn new() -> Self {
-		Self {
-			items: SlabList::new(),
-			vec: Vec::new(),
-		}
+	pub fn pop_first_by_key(&mut self) -> Option<(usize, K, V)>
+	where
+		K: Ord,
+	{
+		self.swap_pop_first_by_key()
 	}
 
+	/// Removes the binding with the smallest key, and returns it with its index.
+	///
+	/// The last binding of the map takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
 	#[inline]
-	pub fn with_capacity(capacity: usize) -> Self {
-		Self {
-			items: SlabList::with_capacity(capacity),
-			vec: Vec::with_capacity(capacity),
-		}
+	pub fn swap_pop_first_by_key(&mut self) -> Option<(usize, K, V)>
+	where
+		K: Ord,
+	{
+		self.pop_by_key(self.first_item_index(), Inner::swap_remove)
 	}
 
+	/// Removes the binding with the smallest key, and returns it with its index.
+	///
+	/// The order of the remaining bindings is preserved.
+	///
+	/// Computes in **O(n)** time (average).
 	#[inline]
-	pub fn capacity(&self) -> usize {
-		self.items.capacity()
+	pub fn shift_pop_first_by_key(&mut self) -> Option<(usize, K, V)>
+	where
+		K: Ord,
+	{
+		self.pop_by_key(self.first_item_index(), Inner::shift_remove)
 	}
 
-	/// Insert the given item.
+	/// Removes the binding with the largest key, and returns it with its index.
 	///
-	/// Computes in **O(1)** time (average).
+	/// This is an alias to [`Self::swap_pop_last_by_key`],
+	/// meaning that this function *disturbs the order of the map*.
+	///
+	/// Computes in **O(log n)** time (average).
 	#[inline]
-	pub fn insert(&mut self, item: Item<K, V>) -> Index {
-		let order = self.vec.len();
-		let index = self.items.push_back(item.order(order));
-		self.vec.push(index);
-		index
+	pub fn pop_last_by_key(&mut self) -> Option<(usize, K, V)>
+	where
+		K: Ord,
+	{
+		self.swap_pop_last_by_key()
 	}
 
-	/// Swap remove the item at the given index.
+	/// Removes the binding with the largest key, and returns it with its index.
 	///
-	/// Computes in **O(1)** time (average).
+	/// The last binding of the map takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
 	#[inline]
-	pub fn swap_remove(&mut self, index: Index) -> Item<K, V> {
-		let item = self.items.remove(index);
-		self.vec.swap_remove(item.order);
-
-		if !self.vec.is_empty() {
-			let swapped_index = self.vec[item.order];
-			self.items.get_mut(swapped_index).unwrap().order = item.order;
-		}
-
-		item.unordered()
+	pub fn swap_pop_last_by_key(&mut self) -> Option<(usize, K, V)>
+	where
+		K: Ord,
+	{
+		self.pop_by_key(self.last_item_index(), Inner::swap_remove)
 	}
 
-	/// Shift remove the item at the given index.
+	/// Removes the binding with the largest key, and returns it with its index.
+	///
+	/// The order of the remaining bindings is preserved.
 	///
 	/// Computes in **O(n)** time (average).
 	#[inline]
-	pub fn shift_remove(&mut self, index: Index) -> Item<K, V> {
-		let item = self.items.remove(index);
-		self.vec.remove(item.order);
+	pub fn shift_pop_last_by_key(&mut self) -> Option<(usize, K, V)>
+	where
+		K: Ord,
+	{
+		self.pop_by_key(self.last_item_index(), Inner::shift_remove)
+	}
+
+	/// Removes the bindings whose index is marked,
+	/// and passes them to `f` with their index, by order of insertion.
+	///
+	/// `marked[i]` tells if the binding at index `start + i` must be removed.
+	/// Bindings outside of this window are kept.
+	/// The remaining bindings keep their relative order,
+	/// and the inner list is compacted once, starting from `start`.
+	///
+	/// When few bindings are removed they are removed from the B-Tree one by one,
+	/// otherwise the B-Tree is rebuilt from the remaining items.
+	///
+	/// Computes in **O(min(k log n, n) + n - start)** time (average),
+	/// where `k` is the number of removed bindings.
+	fn remove_marked<F>(&mut self, start: usize, marked: &[bool], mut f: F)
+	where
+		K: Ord,
+		F: FnMut(usize, K, V),
+	{
+		if self.btree_remove_marked(start, marked) == 0 {
+			return;
+		}
 
-		let mut i = item.order;
-		for &shifted_index in &self.vec[item.order..] {
-			self.items.get_mut(shifted_index).unwrap().order = i;
-			i += 1;
+		let is_marked = |order: usize| order >= start && marked.get(order - start) == Some(&true);
+		let mut new_order = start;
+		for order in start..self.inner.vec.len() {
+			let index = self.inner.vec[order];
+			if is_marked(order) {
+				let item = self.inner.items.remove(index).unordered();
+				f(order, item.key, item.value)
+			} else {
+				self.inner.vec[new_order] = index;
+				self.inner.items.get_mut(index).unwrap().order = new_order;
+				new_order += 1
+			}
 		}
 
-		item.unordered()
+		self.inner.vec.truncate(new_order)
 	}
-}
 
-pub struct Iter<'a, K, V> {
-	inner: &'a Inner<K, V>,
-	indexes: std::slice::Iter<'a, Index>,
-}
+	/// Removes from the B-Tree the items whose index is marked,
+	/// leaving them in the inner list.
+	///
+	/// `marked[i]` tells if the item at index `start + i` must be removed.
+	/// When few items are removed they are removed one by one,
+	/// otherwise the B-Tree is rebuilt from the remaining items.
+	///
+	/// Returns the number of removed items.
+	///
+	/// Computes in **O(min(k log n, n))** time (average),
+	/// where `k` is the number of removed items.
+	fn btree_remove_marked(&mut self, start: usize, marked: &[bool]) -> usize
+	where
+		K: Ord,
+	{
+		let indexes: Vec<Index> = marked
+			.iter()
+			.enumerate()
+			.filter(|&(_, &m)| m)
+			.map(|(i, _)| self.inner.vec[start + i])
+			.collect();
+
+		let is_marked = |order: usize| order >= start && marked.get(order - start) == Some(&true);
+		self.btree_remove_items(&indexes, |item| is_marked(item.order));
+		indexes.len()
+	}
 
-impl<'a, K, V> Iterator for Iter<'a, K, V> {
-	type Item = (&'a K, &'a V);
+	/// Removes the given items from the B-Tree, leaving them in the inner list.
+	///
+	/// `is_removed` must tell if an item is one of them.
+	/// When few items are removed they are removed one by one,
+	/// otherwise the B-Tree is rebuilt from the remaining items.
+	///
+	/// Computes in **O(min(k log n, n))** time (average),
+	/// where `k` is the number of removed items.
+	fn btree_remove_items<P>(&mut self, indexes: &[Index], is_removed: P)
+	where
+		K: Ord,
+		P: Fn(&item::Ordered<K, V>) -> bool,
+	{
+		if indexes.is_empty() {
+			return;
+		}
 
-	#[inline]
-	fn size_hint(&self) -> (usize, Option<usize>) {
-		self.indexes.size_hint()
+		let log_len = (usize::BITS - self.len().leading_zeros()) as usize;
+		if indexes.len().saturating_mul(log_len) < self.len() {
+			for &index in indexes {
+				self.btree_remove_item(index)
+			}
+		} else {
+			let items = &self.inner.items;
+			let kept: Vec<Index> = sorted::Indexes::new(self.btree())
+				.filter(|&index| !is_removed(items.get(index).unwrap()))
+				.collect();
+			self.nodes.clear();
+			self.root = node::build(&mut self.nodes, &kept);
+		}
 	}
 
+	/// Retains only the bindings for which `keep` returns `true`.
+	///
+	/// `keep` is called once for each binding, by order of insertion,
+	/// and may modify the value.
+	/// The remaining bindings keep their relative order.
+	///
+	/// Computes in **O(n)** time.
 	#[inline]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next()
-			.map(|index| self.inner.items.get(*index).unwrap().as_pair())
+	pub fn retain<F>(&mut self, mut keep: F)
+	where
+		K: Ord,
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		let items = &mut self.inner.items;
+		let marked: Vec<bool> = self
+			.inner
+			.vec
+			.iter()
+			.map(|&index| {
+				let item = items.get_mut(index).unwrap();
+				!keep(&item.key, &mut item.value)
+			})
+			.collect();
+
+		self.remove_marked(0, &marked, |_, _, _| ())
 	}
 
+	/// Splits the map in two at the given key.
+	///
+	/// Returns a new map containing every binding whose key is greater than
+	/// or equal to `key`, in the same relative order.
+	/// `self` is left with the bindings whose key is lower than `key`,
+	/// also in the same relative order.
+	///
+	/// The B-Tree is split along the path to the key,
+	/// and the moved nodes are transferred to the new map as they are.
+	///
+	/// Computes in **O(k + log n + n - i)** time (average),
+	/// where `k` is the number of moved bindings
+	/// and `i` the lowest index of the moved bindings.
 	#[inline]
-	fn last(mut self) -> Option<Self::Item> {
-		self.next_back()
+	pub fn split_off_key<Q: ?Sized>(&mut self, key: &Q) -> Self
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		let rank = self.btree().count_before(Bound::Included(key));
+		if rank == 0 {
+			// Every binding moves: hand over the buffers as they are,
+			// and leave `self` with the same capacity.
+			let mut tail = Self::with_capacity(self.capacity());
+			std::mem::swap(self, &mut tail);
+			return tail;
+		}
+
+		if rank == self.len() {
+			return Self::new();
+		}
+
+		let tree = node::Tree::new(&self.nodes, self.root.unwrap());
+		let (left, first, right) = node::split(&mut self.nodes, tree, rank);
+		self.root = left.map(|tree| tree.root);
+
+		let right_btree = Ref::new(&self.nodes, &self.inner, right.map(|tree| tree.root));
+		let right_indexes: Vec<Index> = sorted::Indexes::new(right_btree).collect();
+		let count = self
+			.inner
+			.detach(std::iter::once(first).chain(right_indexes));
+
+		let mut tail = Self::with_capacity(count);
+		tail.inner.vec.resize(count, 0);
+		let items = &mut self.inner.items;
+		let tail_inner = &mut tail.inner;
+		let mut move_item = |index| tail_inner.insert_ordered(items.remove(index));
+		let first = move_item(first);
+		let right = right.map(|tree| node::Tree {
+			root: node::transfer(
+				&mut self.nodes,
+				&mut tail.nodes,
+				tree.root,
+				None,
+				&mut move_item,
+			),
+			height: tree.height,
+		});
+
+		tail.root = Some(node::join(&mut tail.nodes, None, first, right).root);
+		tail
 	}
-}
 
-impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+	/// Moves all the bindings of `other` into `self`, leaving `other` empty.
+	///
+	/// The bindings of `other` are appended in their order of insertion,
+	/// following the semantics of [`Self::insert`]:
+	/// if a key is already in `self`, its value is replaced and its index is kept.
+	///
+	/// If the keys of `other` are all greater, or all lower, than the keys of `self`,
+	/// the nodes of `other` are transferred to `self` as they are,
+	/// and both B-Trees are joined.
+	/// Otherwise, when many bindings are moved, the B-Tree is rebuilt by merging both
+	/// B-Trees in key order instead of inserting each binding separately.
+	///
+	/// Computes in **O(m + log n)** time (average) when the key ranges do not overlap,
+	/// **O(min(m log(n + m), n + m))** otherwise,
+	/// where `m` is the length of `other`.
 	#[inline]
-	fn next_back(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next_back()
-			.map(|index| self.inner.items.get(*index).unwrap().as_pair())
-	}
-}
+	pub fn append(&mut self, other: &mut Self)
+	where
+		K: Ord,
+	{
+		if self.is_empty() {
+			std::mem::swap(self, other);
+			return;
+		}
 
-impl<'a, K, V> std::iter::FusedIterator for Iter<'a, K, V> {}
-impl<'a, K, V> std::iter::ExactSizeIterator for Iter<'a, K, V> {}
+		if other.is_empty() {
+			return;
+		}
 
-pub struct Keys<'a, K, V> {
-	inner: &'a Inner<K, V>,
-	indexes: std::slice::Iter<'a, Index>,
-}
+		let after =
+			self.last_by_key().map(|(_, key, _)| key) < other.first_by_key().map(|(_, key, _)| key);
+		let before =
+			other.last_by_key().map(|(_, key, _)| key) < self.first_by_key().map(|(_, key, _)| key);
+		if after || before {
+			self.append_disjoint(other, after);
+			return;
+		}
 
-impl<'a, K, V> Iterator for Keys<'a, K, V> {
-	type Item = &'a K;
+		let len = self.len() + other.len();
+		let log_len = (usize::BITS - len.leading_zeros()) as usize;
+		if other.len().saturating_mul(log_len) < len {
+			self.extend(other.drain(..));
+			return;
+		}
 
-	#[inline]
-	fn size_hint(&self) -> (usize, Option<usize>) {
-		self.indexes.size_hint()
-	}
+		self.reserve(other.len());
+
+		// Merge both B-Trees in key order.
+		// `targets` gives, for each binding of `other` by order,
+		// the index of the item in `self` holding the same key, if any.
+		let mut merged: Vec<Result<Index, usize>> = Vec::with_capacity(len);
+		let mut targets: Vec<Option<Index>> = vec![None; other.len()];
+		let mut mine = sorted::Indexes::new(self.btree());
+		let mut pending = mine.next();
+		for theirs in sorted::Indexes::new(other.btree()) {
+			let item = other.inner.items.get(theirs).unwrap();
+			while let Some(index) = pending {
+				if self.inner.items.get(index).unwrap().key >= item.key {
+					break;
+				}
+
+				merged.push(Ok(index));
+				pending = mine.next()
+			}
 
-	#[inline]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next()
-			.map(|index| self.inner.items.get(*index).unwrap().as_key())
+			match pending {
+				Some(index) if self.inner.items.get(index).unwrap().key == item.key => {
+					targets[item.order] = Some(index)
+				}
+				_ => merged.push(Err(item.order)),
+			}
+		}
+		merged.extend(pending.into_iter().chain(mine).map(Ok));
+
+		for (order, &index) in other.inner.vec.iter().enumerate() {
+			let item = other.inner.items.remove(index).unordered();
+			match targets[order] {
+				Some(target) => {
+					self.inner
+						.items
+						.get_mut(target)
+						.unwrap()
+						.replace_value(item.value);
+				}
+				None => targets[order] = Some(self.inner.insert(item)),
+			}
+		}
+		other.clear();
+
+		let sorted: Vec<Index> = merged
+			.into_iter()
+			.map(|source| source.unwrap_or_else(|order| targets[order].unwrap()))
+			.collect();
+		self.nodes.clear();
+		self.root = node::build(&mut self.nodes, &sorted);
 	}
 
-	#[inline]
-	fn last(mut self) -> Option<Self::Item> {
-		self.next_back()
+	/// Moves all the bindings of `other` into `self`, leaving `other` empty,
+	/// assuming that the keys of `other` are all greater than the keys of `self`
+	/// if `after` is `true`, all lower otherwise.
+	///
+	/// The item with the extreme key of `other` is split out of its B-Tree,
+	/// the remaining nodes are transferred to `self` and joined with its B-Tree.
+	///
+	/// Computes in **O(m + log n)** time (average), where `m` is the length of `other`.
+	fn append_disjoint(&mut self, other: &mut Self, after: bool) {
+		self.reserve(other.len());
+		let len = self.len();
+		self.inner.vec.resize(len + other.len(), 0);
+
+		let tree = node::Tree::new(&other.nodes, other.root.take().unwrap());
+		let rank = if after { 0 } else { other.len() - 1 };
+		let (lower, separator, greater) = node::split(&mut other.nodes, tree, rank);
+
+		let items = &mut other.inner.items;
+		let inner = &mut self.inner;
+		let mut move_item = |index| {
+			let mut item = items.remove(index);
+			item.order += len;
+			inner.insert_ordered(item)
+		};
+		let separator = move_item(separator);
+		let moved = lower.or(greater).map(|tree| node::Tree {
+			root: node::transfer(
+				&mut other.nodes,
+				&mut self.nodes,
+				tree.root,
+				None,
+				&mut move_item,
+			),
+			height: tree.height,
+		});
+
+		let tree = Some(node::Tree::new(&self.nodes, self.root.unwrap()));
+		let joined = if after {
+			node::join(&mut self.nodes, tree, separator, moved)
+		} else {
+			node::join(&mut self.nodes, moved, separator, tree)
+		};
+
+		self.root = Some(joined.root);
+		other.clear()
 	}
-}
 
-impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+	/// Converts the values of the map with the given function.
+	///
+	/// The function is called once for each binding, by order of insertion.
+	/// Since the B-Tree does not depend on the values,
+	/// its nodes and the index vector are reused as is,
+	/// and each new binding is stored at the index of the old one.
+	///
+	/// Computes in **O(n)** time.
 	#[inline]
-	fn next_back(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next_back()
-			.map(|index| self.inner.items.get(*index).unwrap().as_key())
+	pub fn map_values<W, F>(self, mut f: F) -> IndexMap<K, W>
+	where
+		F: FnMut(&K, V) -> W,
+	{
+		match self.try_map_values(|key, value| Ok::<W, Infallible>(f(key, value))) {
+			Ok(map) => map,
+			Err(e) => match e {},
+		}
 	}
-}
-
-impl<'a, K, V> std::iter::FusedIterator for Keys<'a, K, V> {}
-impl<'a, K, V> std::iter::ExactSizeIterator for Keys<'a, K, V> {}
-
-pub struct Values<'a, K, V> {
-	inner: &'a Inner<K, V>,
-	indexes: std::slice::Iter<'a, Index>,
-}
 
-impl<'a, K, V> Iterator for Values<'a, K, V> {
-	type Item = &'a V;
+	/// Converts the values of the map with the given fallible function.
+	///
+	/// The function is called once for each binding, by order of insertion,
+	/// until it fails.
+	/// In that case the error is returned and the map is dropped.
+	/// Since the B-Tree does not depend on the values,
+	/// its nodes and the index vector are reused as is,
+	/// and each new binding is stored at the index of the old one.
+	///
+	/// Computes in **O(n)** time.
+	pub fn try_map_values<W, E, F>(self, mut f: F) -> Result<IndexMap<K, W>, E>
+	where
+		F: FnMut(&K, V) -> Result<W, E>,
+	{
+		let Self {
+			nodes,
+			inner: Inner { items, vec },
+			root,
+		} = self;
+
+		let items = items.try_map(vec.iter().copied(), |item| {
+			let value = f(&item.key, item.value)?;
+			Ok(Item::new(item.key, value).order(item.order))
+		})?;
+
+		Ok(IndexMap {
+			nodes,
+			inner: Inner { items, vec },
+			root,
+		})
+	}
 
+	/// Splits the map into the set of its keys and the vector of its values.
+	///
+	/// Both are in insertion order: the value at index `i` in the vector
+	/// belongs to the key at index `i` in the set.
+	/// The set reuses the B-Tree nodes, the index vector and the item indexes
+	/// of the map as they are.
+	///
+	/// Computes in **O(n)** time.
 	#[inline]
-	fn size_hint(&self) -> (usize, Option<usize>) {
-		self.indexes.size_hint()
+	pub fn into_parts(self) -> (IndexSet<K>, Vec<V>) {
+		let mut values = Vec::with_capacity(self.len());
+		let keys = self.map_values(|_, value| values.push(value));
+		(IndexSet::from_map(keys), values)
 	}
 
+	/// Builds a map from a set of keys and a vector of values.
+	///
+	/// The value at index `i` in the vector is bound to the key at index `i` in the set.
+	/// This is the inverse of [`Self::into_parts`].
+	/// The map reuses the B-Tree nodes, the index vector and the item indexes
+	/// of the set as they are.
+	///
+	/// Computes in **O(n)** time.
+	///
+	/// ## Panics
+	///
+	/// Panics if the set and the vector do not have the same length.
 	#[inline]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next()
-			.map(|index| self.inner.items.get(*index).unwrap().as_value())
+	pub fn from_parts(keys: IndexSet<K>, values: Vec<V>) -> Self {
+		assert_eq!(
+			keys.len(),
+			values.len(),
+			"keys and values do not have the same length"
+		);
+
+		let mut values = values.into_iter();
+		keys.into_map().map_values(|_, ()| values.next().unwrap())
 	}
 
+	/// Converts the keys of the map with the given function.
+	///
+	/// The function is called once for each key, by order of insertion.
+	/// Insertion order and values are kept, and the B-Tree is rebuilt for the new keys.
+	///
+	/// Each new binding is stored at the index of the old one,
+	/// so the capacity of the map is kept.
+	///
+	/// Returns an error listing the indexes of the bindings whose new keys collide.
+	/// Collisions are only known once every key is converted:
+	/// in that case the map is dropped, and its storage is lost.
+	///
+	/// Computes in **O(n log n)** time.
 	#[inline]
-	fn last(mut self) -> Option<Self::Item> {
-		self.next_back()
+	pub fn map_keys<K2, F>(self, mut f: F) -> Result<IndexMap<K2, V>, Collision>
+	where
+		K2: Ord,
+		F: FnMut(K) -> K2,
+	{
+		self.rekey(|key, value| (f(key), value))
 	}
-}
 
-impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+	/// Swaps the keys and values of the map.
+	///
+	/// Insertion order is kept, and the B-Tree is rebuilt for the new keys.
+	///
+	/// Each new binding is stored at the index of the old one,
+	/// so the capacity of the map is kept.
+	///
+	/// Returns an error listing the indexes of the bindings with equal values.
+	/// In that case the map is dropped, and its storage is lost.
+	///
+	/// Computes in **O(n log n)** time.
 	#[inline]
-	fn next_back(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next_back()
-			.map(|index| self.inner.items.get(*index).unwrap().as_value())
+	pub fn invert(self) -> Result<IndexMap<V, K>, Collision>
+	where
+		V: Ord,
+	{
+		self.rekey(|key, value| (value, key))
 	}
-}
 
-impl<'a, K, V> std::iter::FusedIterator for Values<'a, K, V> {}
-impl<'a, K, V> std::iter::ExactSizeIterator for Values<'a, K, V> {}
+	/// Converts the bindings of the map with the given function,
+	/// keeping their insertion order.
+	///
+	/// Each new binding is stored at the index of the old one,
+	/// so the capacity and the free slots of the item list are kept,
+	/// and the B-Tree is rebuilt bottom-up in the existing node storage.
+	/// If some new keys collide, the converted bindings are dropped
+	/// along with the storage of the map.
+	fn rekey<K2, V2, F>(self, mut f: F) -> Result<IndexMap<K2, V2>, Collision>
+	where
+		K2: Ord,
+		F: FnMut(K, V) -> (K2, V2),
+	{
+		let Self {
+			mut nodes,
+			inner: Inner { items, vec },
+			..
+		} = self;
+
+		let items = match items.try_map(vec.iter().copied(), |item| {
+			let (key, value) = f(item.key, item.value);
+			Ok::<_, Infallible>(Item::new(key, value).order(item.order))
+		}) {
+			Ok(items) => items,
+			Err(e) => match e {},
+		};
+
+		let mut sorted = vec.clone();
+		sorted.sort_by(|&a, &b| items.get(a).unwrap().key.cmp(&items.get(b).unwrap().key));
+
+		let mut collisions = Vec::new();
+		let mut first = 0;
+		for i in 1..sorted.len() {
+			let a = items.get(sorted[first]).unwrap();
+			let b = items.get(sorted[i]).unwrap();
+			if a.key == b.key {
+				collisions.push((a.order, b.order))
+			} else {
+				first = i
+			}
+		}
 
-pub struct IntoIter<K, V> {
-	indexes: std::vec::IntoIter<Index>,
-	items: SlabList<item::Ordered<K, V>>,
-}
+		if !collisions.is_empty() {
+			return Err(Collision {
+				indexes: collisions,
+			});
+		}
 
-impl<K, V> Iterator for IntoIter<K, V> {
-	type Item = (K, V);
+		nodes.clear();
+		let root = node::build(&mut nodes, &sorted);
+		Ok(IndexMap {
+			nodes,
+			inner: Inner { items, vec },
+			root,
+		})
+	}
 
+	/// Removes all the bindings of the map.
+	///
+	/// The B-Tree nodes are released, but the capacity of the map is kept.
 	#[inline]
-	fn size_hint(&self) -> (usize, Option<usize>) {
-		self.indexes.size_hint()
+	pub fn clear(&mut self) {
+		self.inner.items.clear();
+		self.inner.vec.clear();
+		self.nodes.clear();
+		self.root = None
 	}
 
+	/// Shortens the map, keeping the first `len` bindings.
+	///
+	/// Has no effect if `len` is greater than or equal to the length of the map.
+	///
+	/// Computes in **O(min(k log n, n))** time (average),
+	/// where `k` is the number of removed bindings.
 	#[inline]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next()
-			.map(|index| self.items.remove(index).into_pair())
+	pub fn truncate(&mut self, len: usize)
+	where
+		K: Ord,
+	{
+		match len {
+			0 => self.clear(),
+			_ if len < self.len() => {
+				let marked = vec![true; self.len() - len];
+				self.remove_marked(len, &marked, |_, _, _| ())
+			}
+			_ => (),
+		}
 	}
 
+	/// Removes the bindings in the given range of indexes,
+	/// and returns them by order of insertion.
+	///
+	/// Like [`Vec::drain`], the bindings are moved out of the map as they are
+	/// yielded by the returned iterator, and the bindings that were not yielded
+	/// are dropped with it, even if it is never consumed.
+	/// The following bindings are shifted back, keeping contiguous indexes.
+	///
+	/// Computes in **O(min(k log n, n) + n - start)** time (average),
+	/// where `k` is the number of removed bindings.
+	///
+	/// ## Panics
+	///
+	/// Panics if the start of the range is greater than its end,
+	/// or if the end of the range is greater than the length of the map.
 	#[inline]
-	fn last(mut self) -> Option<Self::Item> {
-		self.next_back()
+	pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<K, V>
+	where
+		K: Ord,
+	{
+		let range = index_range(range, self.len());
+		let marked = vec![true; range.len()];
+		self.btree_remove_marked(range.start, &marked);
+		let indexes: Vec<Index> = self.inner.vec.drain(range.clone()).collect();
+		self.inner.renumber(range.start..self.inner.vec.len());
+
+		Drain {
+			indexes: indexes.into_iter(),
+			items: &mut self.inner.items,
+		}
 	}
-}
 
-impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+	/// Returns the ranks of the bindings whose key is in the given range.
+	///
+	/// Computes in **O(log n)** time.
+	fn key_ranks<Q: ?Sized, R>(&self, range: &R) -> std::ops::Range<usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		let btree = self.btree();
+		let start = btree.count_before(range.start_bound());
+		let end = match range.end_bound() {
+			Bound::Included(key) => btree.count_before(Bound::Excluded(key)),
+			Bound::Excluded(key) => btree.count_before(Bound::Included(key)),
+			Bound::Unbounded => self.len(),
+		};
+
+		start..std::cmp::max(start, end)
+	}
+
+	/// Detaches the items whose rank is in the given range from the B-Tree
+	/// and from the index vector, leaving them in the inner list.
+	///
+	/// The B-Tree is split along the paths to both ends of the range:
+	/// the subtrees in between are detached at once,
+	/// and the remaining parts are joined back.
+	/// The remaining bindings keep their relative order, with contiguous indexes.
+	///
+	/// Returns the indexes of the detached items, by ascending key order.
+	///
+	/// Computes in **O(k + log n + n - i)** time (average),
+	/// where `k` is the number of detached items
+	/// and `i` the lowest index among them.
+	fn detach_ranks(&mut self, ranks: std::ops::Range<usize>) -> Vec<Index> {
+		if ranks.is_empty() {
+			return Vec::new();
+		}
+
+		let tree = node::Tree::new(&self.nodes, self.root.unwrap());
+		let (head, tail) = if ranks.end < self.len() {
+			let (head, separator, tail) = node::split(&mut self.nodes, tree, ranks.end);
+			(head.unwrap(), Some((separator, tail)))
+		} else {
+			(tree, None)
+		};
+
+		let (left, first, removed) = node::split(&mut self.nodes, head, ranks.start);
+		self.root = match tail {
+			Some((separator, tail)) => {
+				Some(node::join(&mut self.nodes, left, separator, tail).root)
+			}
+			None => left.map(|tree| tree.root),
+		};
+
+		let mut indexes = Vec::with_capacity(ranks.len());
+		indexes.push(first);
+		if let Some(tree) = removed {
+			node::remove_subtree(&mut self.nodes, tree.root, &mut |index| indexes.push(index))
+		}
+
+		self.inner.detach(indexes.iter().copied());
+		indexes
+	}
+
+	/// Removes the bindings whose key is in the given range,
+	/// and returns them by ascending key order.
+	///
+	/// As with [`Self::drain`], the bindings are moved out of the map as they are
+	/// yielded by the returned iterator, and the bindings that were not yielded
+	/// are dropped with it.
+	/// The remaining bindings keep their relative order, with contiguous indexes.
+	///
+	/// Computes in **O(k + log n + n - i)** time (average),
+	/// where `k` is the number of removed bindings
+	/// and `i` the lowest index of the removed bindings.
 	#[inline]
-	fn next_back(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next_back()
-			.map(|index| self.items.remove(index).into_pair())
+	pub fn drain_range<Q: ?Sized, R>(&mut self, range: R) -> Drain<K, V>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		let ranks = self.key_ranks(&range);
+		let indexes = self.detach_ranks(ranks);
+
+		Drain {
+			indexes: indexes.into_iter(),
+			items: &mut self.inner.items,
+		}
 	}
-}
 
-impl<'a, K, V> std::iter::FusedIterator for IntoIter<K, V> {}
-impl<'a, K, V> std::iter::ExactSizeIterator for IntoIter<K, V> {}
+	/// Removes the bindings whose key is in the given range,
+	/// and returns the number of removed bindings.
+	///
+	/// The remaining bindings keep their relative order, with contiguous indexes.
+	///
+	/// Computes in **O(k + log n + n - i)** time (average),
+	/// where `k` is the number of removed bindings
+	/// and `i` the lowest index of the removed bindings.
+	#[inline]
+	pub fn remove_range<Q: ?Sized, R>(&mut self, range: R) -> usize
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		self.drain_range(range).len()
+	}
 
-pub struct IntoKeys<K, V> {
-	indexes: std::vec::IntoIter<Index>,
-	items: SlabList<item::Ordered<K, V>>,
-}
+	/// Returns the sorted and deduplicated indexes of the given keys.
+	///
+	/// Keys that are not in the map are ignored.
+	fn indexes_of<'q, Q: 'q + ?Sized, I>(&self, keys: I) -> Vec<usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		I: IntoIterator<Item = &'q Q>,
+	{
+		let mut indexes: Vec<usize> = keys
+			.into_iter()
+			.filter_map(|key| self.get_full(key).map(|(index, _, _)| index))
+			.collect();
+		indexes.sort_unstable();
+		indexes.dedup();
+		indexes
+	}
 
-impl<K, V> Iterator for IntoKeys<K, V> {
-	type Item = K;
+	/// Removes the bindings of all the given keys,
+	/// and returns them with their index, by ascending index.
+	///
+	/// Keys that are not in the map, or repeated, are ignored.
+	/// The remaining bindings keep their relative order, with contiguous indexes.
+	///
+	/// Computes in **O(k log n + n - i)** time (average),
+	/// where `k` is the number of keys
+	/// and `i` the lowest index of the removed bindings.
+	pub fn shift_remove_many<'q, Q: 'q + ?Sized, I>(&mut self, keys: I) -> Vec<(usize, K, V)>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+		I: IntoIterator<Item = &'q Q>,
+	{
+		let indexes = self.indexes_of(keys);
+		let (start, end) = match (indexes.first(), indexes.last()) {
+			(Some(&start), Some(&end)) => (start, end + 1),
+			_ => return Vec::new(),
+		};
+
+		let mut marked = vec![false; end - start];
+		for &index in &indexes {
+			marked[index - start] = true
+		}
+
+		let mut removed = Vec::with_capacity(indexes.len());
+		self.remove_marked(start, &marked, |index, key, value| {
+			removed.push((index, key, value))
+		});
+		removed
+	}
+
+	/// Removes the bindings of all the given keys, and returns them with their index.
+	///
+	/// This is the same as calling [`Self::swap_remove_full`] on each key in turn:
+	/// the last binding of the map takes the place of each removed binding,
+	/// and the index returned with a binding is its index when it is removed.
+	/// Bindings are returned in the order of the keys.
+	/// Keys that are not in the map, or already removed, are ignored.
+	///
+	/// The bindings are first found and swapped out of the index vector,
+	/// then they are removed from the B-Tree at once,
+	/// which is rebuilt if many bindings are removed.
+	///
+	/// Computes in **O(k log n)** time (average), where `k` is the number of keys.
+	pub fn swap_remove_many<'q, Q: 'q + ?Sized, I>(&mut self, keys: I) -> Vec<(usize, K, V)>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+		I: IntoIterator<Item = &'q Q>,
+	{
+		// Orders are lower than `vec.len()`, so `usize::MAX` marks the removed items.
+		let mut removed: Vec<(usize, Index)> = Vec::new();
+		for key in keys {
+			let index = match self.btree().get(key) {
+				Some(item) => item.into_index(),
+				None => continue,
+			};
+
+			let item = self.inner.items.get_mut(index).unwrap();
+			if item.order == usize::MAX {
+				continue;
+			}
+
+			let order = std::mem::replace(&mut item.order, usize::MAX);
+			self.inner.vec.swap_remove(order);
+			if order < self.inner.vec.len() {
+				let swapped_index = self.inner.vec[order];
+				self.inner.items.get_mut(swapped_index).unwrap().order = order;
+			}
+
+			removed.push((order, index))
+		}
+
+		let indexes: Vec<Index> = removed.iter().map(|&(_, index)| index).collect();
+		self.btree_remove_items(&indexes, |item| item.order == usize::MAX);
+		removed
+			.into_iter()
+			.map(|(order, index)| {
+				let item = self.inner.items.remove(index);
+				(order, item.key, item.value)
+			})
+			.collect()
+	}
 
+	/// Splits the map in two at the given index.
+	///
+	/// Returns a new map containing the bindings from index `at` onward,
+	/// in the same order.
+	/// `self` is left with the bindings `[0, at)`.
+	///
+	/// Computes in **O(min(k log n, n) + k log k)** time (average),
+	/// where `k` is the number of moved bindings.
+	///
+	/// ## Panics
+	///
+	/// Panics if `at` is greater than the length of the map.
 	#[inline]
-	fn size_hint(&self) -> (usize, Option<usize>) {
-		self.indexes.size_hint()
+	pub fn split_off(&mut self, at: usize) -> Self
+	where
+		K: Ord,
+	{
+		assert!(
+			at <= self.len(),
+			"index {} out of bounds (len is {})",
+			at,
+			self.len()
+		);
+		let mut tail = Self::with_capacity(self.len() - at);
+		tail.build(self.drain(at..));
+		tail
 	}
 
+	/// Sorts the bindings of the map by key.
+	///
+	/// Only the insertion order changes, the B-Tree is left untouched.
+	///
+	/// Computes in **O(n)** time, by walking the B-Tree.
 	#[inline]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next()
-			.map(|index| self.items.remove(index).into_key())
+	pub fn sort_keys(&mut self) {
+		let indexes: Vec<Index> = sorted::Indexes::new(self.btree()).collect();
+		self.inner.vec.clear();
+		self.inner.vec.extend(indexes);
+		self.inner.renumber(0..self.len())
 	}
 
+	/// Sorts the bindings of the map with the given comparison function.
+	///
+	/// The sort is stable.
+	/// Only the insertion order changes, the B-Tree is left untouched.
+	/// If `cmp` panics, the map is left in an unspecified but consistent order.
+	///
+	/// Computes in **O(n log n)** time.
 	#[inline]
-	fn last(mut self) -> Option<Self::Item> {
-		self.next_back()
+	pub fn sort_by<F>(&mut self, mut cmp: F)
+	where
+		F: FnMut(&K, &V, &K, &V) -> Ordering,
+	{
+		let guard = RenumberGuard(&mut self.inner);
+		let items = &guard.0.items;
+		guard.0.vec.sort_by(|&a, &b| {
+			let a = items.get(a).unwrap();
+			let b = items.get(b).unwrap();
+			cmp(&a.key, &a.value, &b.key, &b.value)
+		})
 	}
-}
 
-impl<K, V> DoubleEndedIterator for IntoKeys<K, V> {
+	/// Sorts the bindings of the map with the given comparison function.
+	///
+	/// The sort is unstable: bindings comparing equal may be reordered.
+	/// Only the insertion order changes, the B-Tree is left untouched.
+	/// If `cmp` panics, the map is left in an unspecified but consistent order.
+	///
+	/// Computes in **O(n log n)** time.
 	#[inline]
-	fn next_back(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next_back()
-			.map(|index| self.items.remove(index).into_key())
+	pub fn sort_unstable_by<F>(&mut self, mut cmp: F)
+	where
+		F: FnMut(&K, &V, &K, &V) -> Ordering,
+	{
+		let guard = RenumberGuard(&mut self.inner);
+		let items = &guard.0.items;
+		guard.0.vec.sort_unstable_by(|&a, &b| {
+			let a = items.get(a).unwrap();
+			let b = items.get(b).unwrap();
+			cmp(&a.key, &a.value, &b.key, &b.value)
+		})
 	}
-}
 
-impl<'a, K, V> std::iter::FusedIterator for IntoKeys<K, V> {}
-impl<'a, K, V> std::iter::ExactSizeIterator for IntoKeys<K, V> {}
+	/// Reverses the order of the bindings of the map.
+	///
+	/// Only the insertion order changes, the B-Tree is left untouched.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	pub fn reverse(&mut self) {
+		self.inner.vec.reverse();
+		self.inner.renumber(0..self.len())
+	}
 
-pub struct IntoValues<K, V> {
-	indexes: std::vec::IntoIter<Index>,
-	items: SlabList<item::Ordered<K, V>>,
-}
+	/// Moves the binding at index `from` to index `to`,
+	/// shifting the bindings in between.
+	///
+	/// Only the insertion order changes, the B-Tree is left untouched.
+	///
+	/// Computes in **O(|to - from|)** time (average).
+	///
+	/// ## Panics
+	///
+	/// Panics if `from` or `to` are out of bounds.
+	#[inline]
+	pub fn move_index(&mut self, from: usize, to: usize) {
+		let len = self.len();
+		assert!(from < len, "index {} out of bounds (len is {})", from, len);
+		assert!(to < len, "index {} out of bounds (len is {})", to, len);
+		if from < to {
+			self.inner.vec[from..=to].rotate_left(1);
+			self.inner.renumber(from..(to + 1))
+		} else {
+			self.inner.vec[to..=from].rotate_right(1);
+			self.inner.renumber(to..(from + 1))
+		}
+	}
 
-impl<K, V> Iterator for IntoValues<K, V> {
-	type Item = V;
+	/// Swaps the bindings at indexes `a` and `b`.
+	///
+	/// Only the insertion order changes, the B-Tree is left untouched.
+	///
+	/// Computes in **O(1)** time (average).
+	///
+	/// ## Panics
+	///
+	/// Panics if `a` or `b` are out of bounds.
+	#[inline]
+	pub fn swap_indices(&mut self, a: usize, b: usize) {
+		let len = self.len();
+		assert!(a < len, "index {} out of bounds (len is {})", a, len);
+		assert!(b < len, "index {} out of bounds (len is {})", b, len);
+		self.inner.vec.swap(a, b);
+		self.inner.renumber(a..(a + 1));
+		self.inner.renumber(b..(b + 1))
+	}
 
+	/// Inserts a key-value pair in the map at the given index,
+	/// shifting the following bindings.
+	///
+	/// If the key is already in the map, its binding is moved to the given index,
+	/// its value is replaced and the old value is returned.
+	///
+	/// Computes in **O(log n + |n - index|)** time (average).
+	///
+	/// ## Panics
+	///
+	/// Panics if `index` is out of bounds:
+	/// valid indexes are `0..=len` when inserting a new key,
+	/// and `0..len` when moving an existing one.
 	#[inline]
-	fn size_hint(&self) -> (usize, Option<usize>) {
-		self.indexes.size_hint()
+	pub fn shift_insert(&mut self, index: usize, key: K, value: V) -> Option<V>
+	where
+		K: Ord,
+	{
+		let len = self.len();
+		match self.entry(key) {
+			Entry::Occupied(mut entry) => {
+				assert!(
+					index < len,
+					"index {} out of bounds (len is {})",
+					index,
+					len
+				);
+				let from = entry.index();
+				let old_value = entry.insert(value);
+				self.move_index(from, index);
+				Some(old_value)
+			}
+			Entry::Vacant(entry) => {
+				assert!(
+					index <= len,
+					"index {} out of bounds (len is {})",
+					index,
+					len
+				);
+				entry.insert(value);
+				self.move_index(len, index);
+				None
+			}
+		}
 	}
 
+	/// Inserts a key-value pair in the map just before the binding of `anchor`.
+	///
+	/// If the key is already in the map, its binding is moved, its value is
+	/// replaced and the old value is returned.
+	/// Returns the new index of the binding with the old value,
+	/// or gives back the binding if `anchor` is not in the map.
+	///
+	/// Computes in **O(log n + k)** time (average),
+	/// where `k` is the number of shifted bindings.
 	#[inline]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next()
-			.map(|index| self.items.remove(index).into_value())
+	pub fn insert_before<Q: ?Sized>(
+		&mut self,
+		anchor: &Q,
+		key: K,
+		value: V,
+	) -> Result<(usize, Option<V>), (K, V)>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		self.insert_next_to(anchor, false, key, value)
+	}
+
+	/// Inserts a key-value pair in the map just after the binding of `anchor`.
+	///
+	/// If the key is already in the map, its binding is moved, its value is
+	/// replaced and the old value is returned.
+	/// Returns the new index of the binding with the old value,
+	/// or gives back the binding if `anchor` is not in the map.
+	///
+	/// Computes in **O(log n + k)** time (average),
+	/// where `k` is the number of shifted bindings.
+	#[inline]
+	pub fn insert_after<Q: ?Sized>(
+		&mut self,
+		anchor: &Q,
+		key: K,
+		value: V,
+	) -> Result<(usize, Option<V>), (K, V)>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		self.insert_next_to(anchor, true, key, value)
+	}
+
+	/// Inserts a key-value pair next to the binding of `anchor`.
+	fn insert_next_to<Q: ?Sized>(
+		&mut self,
+		anchor: &Q,
+		after: bool,
+		key: K,
+		value: V,
+	) -> Result<(usize, Option<V>), (K, V)>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		let anchor = match self.get_full(anchor) {
+			Some((anchor, _, _)) => anchor,
+			None => return Err((key, value)),
+		};
+
+		let (index, old_value) = self.insert_full(key, value);
+		let target = match (index.cmp(&anchor), after) {
+			(Ordering::Equal, _) => index,
+			(Ordering::Less, false) => anchor - 1,
+			(Ordering::Less, true) | (Ordering::Greater, false) => anchor,
+			(Ordering::Greater, true) => anchor + 1,
+		};
+
+		self.move_index(index, target);
+		Ok((target, old_value))
+	}
+
+	/// Rearranges the bindings of the map according to the given permutation.
+	///
+	/// After the call, the binding at index `i` is the one that was
+	/// at index `permutation[i]` before.
+	/// Only the insertion order changes, the B-Tree is left untouched,
+	/// and the bindings are rearranged in place without affecting the capacity.
+	///
+	/// Returns an error, and leaves the map unchanged,
+	/// if `permutation` is not a permutation of `0..len`.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	pub fn permute(&mut self, permutation: &[usize]) -> Result<(), PermutationError> {
+		let len = self.len();
+		if permutation.len() != len {
+			return Err(PermutationError::Length {
+				expected: len,
+				found: permutation.len(),
+			});
+		}
+
+		let mut seen = vec![false; len];
+		for &i in permutation {
+			match seen.get_mut(i) {
+				Some(true) => return Err(PermutationError::Duplicate(i)),
+				Some(seen) => *seen = true,
+				None => return Err(PermutationError::OutOfBounds(i)),
+			}
+		}
+
+		// Store the new index of each binding in its item,
+		// then move each item index to its place by following the cycles.
+		for (i, &j) in permutation.iter().enumerate() {
+			let index = self.inner.vec[j];
+			self.inner.items.get_mut(index).unwrap().order = i
+		}
+
+		for i in 0..len {
+			loop {
+				let order = self.inner.items.get(self.inner.vec[i]).unwrap().order;
+				if order == i {
+					break;
+				}
+
+				self.inner.vec.swap(i, order)
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Rotates the bindings of the map so that the binding at index `mid`
+	/// becomes the first one.
+	///
+	/// Computes in **O(n)** time.
+	///
+	/// ## Panics
+	///
+	/// Panics if `mid` is greater than the length of the map.
+	#[inline]
+	pub fn rotate_left(&mut self, mid: usize) {
+		self.inner.vec.rotate_left(mid);
+		self.inner.renumber(0..self.len())
+	}
+
+	/// Rotates the bindings of the map so that the last `k` bindings
+	/// become the first ones.
+	///
+	/// Computes in **O(n)** time.
+	///
+	/// ## Panics
+	///
+	/// Panics if `k` is greater than the length of the map.
+	#[inline]
+	pub fn rotate_right(&mut self, k: usize) {
+		self.inner.vec.rotate_right(k);
+		self.inner.renumber(0..self.len())
+	}
+
+	/// Returns an iterator over the bindings of the map.
+	///
+	/// Bindings are iterated by order of insertion in the map.
+	#[inline]
+	pub fn iter(&self) -> Iter<K, V> {
+		Iter {
+			inner: &self.inner,
+			indexes: self.inner.vec.iter(),
+		}
+	}
+
+	/// Returns an iterator over the bindings of the map, with their index.
+	///
+	/// Bindings are iterated by order of insertion in the map.
+	#[inline]
+	pub fn iter_full(&self) -> IterFull<K, V> {
+		IterFull {
+			inner: &self.inner,
+			indexes: self.inner.vec.iter(),
+		}
+	}
+
+	/// Returns an iterator over the bindings of the map,
+	/// with mutable references to the values.
+	///
+	/// Bindings are iterated by order of insertion in the map.
+	#[inline]
+	pub fn iter_mut(&mut self) -> IterMut<K, V> {
+		IterMut {
+			items: ItemsMut::new(&mut self.inner.items),
+			indexes: self.inner.vec.iter(),
+		}
+	}
+
+	/// Returns an iterator over the keys of the map.
+	///
+	/// Keys are iterated by order of insertion in the map.
+	#[inline]
+	pub fn keys(&self) -> Keys<K, V> {
+		Keys {
+			inner: &self.inner,
+			indexes: self.inner.vec.iter(),
+		}
+	}
+
+	/// Returns an iterator over the values of the map.
+	///
+	/// Values are iterated by order of insertion in the map.
+	#[inline]
+	pub fn values(&self) -> Values<K, V> {
+		Values {
+			inner: &self.inner,
+			indexes: self.inner.vec.iter(),
+		}
+	}
+
+	/// Returns an iterator over mutable references to the values of the map.
+	///
+	/// Values are iterated by order of insertion in the map.
+	#[inline]
+	pub fn values_mut(&mut self) -> ValuesMut<K, V> {
+		ValuesMut {
+			items: ItemsMut::new(&mut self.inner.items),
+			indexes: self.inner.vec.iter(),
+		}
+	}
+
+	/// Returns an iterator over the bindings of the map, with their index.
+	///
+	/// Bindings are iterated by ascending key order.
+	#[inline]
+	pub fn iter_sorted(&self) -> SortedIter<K, V> {
+		SortedIter::new(&self.inner, sorted::Indexes::new(self.btree()))
+	}
+
+	/// Returns an iterator over the keys of the map.
+	///
+	/// Keys are iterated by ascending order.
+	#[inline]
+	pub fn keys_sorted(&self) -> SortedKeys<K, V> {
+		SortedKeys::new(self.iter_sorted())
+	}
+
+	/// Returns an iterator over the bindings of the map whose key is
+	/// in the given range, with their index.
+	///
+	/// Bindings are iterated by ascending key order.
+	/// The range is found in **O(log n)** time.
+	#[inline]
+	pub fn range<Q: ?Sized, R>(&self, range: R) -> Range<K, V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		let btree = self.btree();
+		let bounds = btree.range_bounds(&range);
+		Range::new(&self.inner, sorted::Indexes::range(btree, bounds))
+	}
+
+	/// Returns an iterator over the bindings of the map whose key is
+	/// in the given range, with their index and a mutable reference to their value.
+	///
+	/// Bindings are iterated by ascending key order.
+	/// The range is found in **O(log n)** time.
+	#[inline]
+	pub fn range_mut<Q: ?Sized, R>(&mut self, range: R) -> RangeMut<K, V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		let bounds = self.btree().range_bounds(&range);
+		let inner: *mut Inner<K, V> = &mut self.inner;
+		// SAFETY: `inner` comes from `self.inner`, borrowed mutably for the lifetime
+		// of the iterator. The item pointer is taken before the B-Tree reference,
+		// which then only walks the nodes: the items are only reached through
+		// `items`, which hands out each of them at most once.
+		let items = ItemsMut::new(unsafe { &mut (*inner).items });
+		let btree = Ref::new(&self.nodes, unsafe { &*inner }, self.root);
+		RangeMut::new(items, sorted::Indexes::range(btree, bounds))
+	}
+
+	/// Returns the rank of the given key, that is the number of keys in the
+	/// map that are lower than it, or `None` if the key is not in the map.
+	///
+	/// Computes in **O(log n)** time.
+	#[inline]
+	pub fn rank_of<Q: ?Sized>(&self, key: &Q) -> Option<usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.btree().rank(key).ok()
+	}
+
+	/// Returns the binding with the given rank, with its index.
+	///
+	/// The binding of rank `0` is the one with the smallest key.
+	///
+	/// Computes in **O(log n)** time.
+	#[inline]
+	pub fn get_by_rank(&self, rank: usize) -> Option<(usize, &K, &V)> {
+		let btree = self.btree();
+		btree
+			.address_of_rank(rank)
+			.map(|address| btree.item_at(address).as_full())
+	}
+
+	/// Returns the number of bindings whose key is in the given range.
+	///
+	/// Computes in **O(log n)** time.
+	#[inline]
+	pub fn count_range<Q: ?Sized, R>(&self, range: R) -> usize
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		self.key_ranks(&range).len()
+	}
+
+	/// Returns the binding at the given quantile of the key order, with its index.
+	///
+	/// This is the binding whose rank is the nearest to `q * (len - 1)`,
+	/// where `q` is clamped to `[0, 1]`:
+	/// `quantile(0.0)` returns the binding with the smallest key,
+	/// `quantile(0.5)` the median and `quantile(1.0)` the binding with the largest key.
+	///
+	/// Returns `None` if the map is empty.
+	///
+	/// Computes in **O(log n)** time.
+	///
+	/// ## Panics
+	///
+	/// Panics if `q` is NaN.
+	#[inline]
+	pub fn quantile(&self, q: f64) -> Option<(usize, &K, &V)> {
+		assert!(!q.is_nan(), "quantile is NaN");
+		if self.is_empty() {
+			return None;
+		}
+
+		let rank = (q.clamp(0.0, 1.0) * (self.len() - 1) as f64).round() as usize;
+		self.get_by_rank(rank)
+	}
+
+	/// Consumes the map and returns an iterator over its keys.
+	///
+	/// Keys are iterated by order of insertion in the map.
+	#[inline]
+	pub fn into_keys(self) -> IntoKeys<K, V> {
+		IntoKeys {
+			indexes: self.inner.vec.into_iter(),
+			items: self.inner.items,
+		}
+	}
+
+	/// Consumes the map and returns an iterator over its values.
+	///
+	/// Values are iterated by order of insertion in the map.
+	#[inline]
+	pub fn into_values(self) -> IntoValues<K, V> {
+		IntoValues {
+			indexes: self.inner.vec.into_iter(),
+			items: self.inner.items,
+		}
+	}
+}
+
+/// Error returned by [`IndexMap::permute`] when the given indexes
+/// are not a permutation of the map indexes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PermutationError {
+	/// The permutation does not have the same length as the map.
+	Length { expected: usize, found: usize },
+
+	/// The given index is out of bounds.
+	OutOfBounds(usize),
+
+	/// The given index appears more than once.
+	Duplicate(usize),
+}
+
+impl fmt::Display for PermutationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Length { expected, found } => {
+				write!(f, "permutation has length {}, expected {}", found, expected)
+			}
+			Self::OutOfBounds(i) => write!(f, "permutation index {} out of bounds", i),
+			Self::Duplicate(i) => write!(f, "permutation index {} appears more than once", i),
+		}
+	}
+}
+
+impl std::error::Error for PermutationError {}
+
+/// Error returned by [`IndexMap::map_keys`] and [`IndexMap::invert`]
+/// when several bindings end up with the same key.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Collision {
+	indexes: Vec<(usize, usize)>,
+}
+
+impl Collision {
+	/// Returns the pairs of colliding indexes.
+	///
+	/// Each pair is made of the lowest index with a given key,
+	/// and of another index with the same key.
+	/// Pairs are sorted by key.
+	#[inline]
+	pub fn indexes(&self) -> &[(usize, usize)] {
+		&self.indexes
+	}
+}
+
+impl fmt::Display for Collision {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (a, b) = self.indexes[0];
+		write!(f, "bindings {} and {} have the same key", a, b)?;
+		if self.indexes.len() > 1 {
+			write!(f, " ({} collisions)", self.indexes.len())?;
+		}
+
+		Ok(())
+	}
+}
+
+impl std::error::Error for Collision {}
+
+/// Error returned by [`IndexMap::try_from_iter`] when a key appears twice.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DuplicateKey<K> {
+	/// Index of the first binding with this key.
+	pub first_index: usize,
+
+	/// Index of the duplicate binding in the input iterator.
+	pub second_index: usize,
+
+	/// The duplicate key.
+	pub key: K,
+}
+
+impl<K: fmt::Debug> fmt::Display for DuplicateKey<K> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"duplicate key {:?} at indexes {} and {}",
+			self.key, self.first_index, self.second_index
+		)
+	}
+}
+
+impl<K: fmt::Debug> std::error::Error for DuplicateKey<K> {}
+
+impl<K, V> Default for IndexMap<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K: Clone, V: Clone> Clone for IndexMap<K, V> {
+	/// Clones the map, preserving the index of each binding.
+	///
+	/// The B-Tree nodes and items are copied as they are,
+	/// without re-inserting any binding.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	fn clone(&self) -> Self {
+		Self {
+			nodes: self.nodes.clone(),
+			inner: self.inner.clone(),
+			root: self.root,
+		}
+	}
+
+	/// Overwrites `self` with a clone of `source`,
+	/// reusing the allocations of `self`.
+	#[inline]
+	fn clone_from(&mut self, source: &Self) {
+		self.nodes.clone_from(&source.nodes);
+		self.inner.clone_from(&source.inner);
+		self.root = source.root
+	}
+}
+
+impl<K1, V1, K2, V2> PartialEq<IndexMap<K2, V2>> for IndexMap<K1, V1>
+where
+	K1: PartialEq<K2>,
+	V1: PartialEq<V2>,
+{
+	/// Checks that both maps contain the same bindings, regardless of their index.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	fn eq(&self, other: &IndexMap<K2, V2>) -> bool {
+		self.len() == other.len() && self.sorted_items().eq(other.sorted_items())
+	}
+}
+
+impl<K: Eq, V: Eq> Eq for IndexMap<K, V> {}
+
+impl<K: Hash, V: Hash> Hash for IndexMap<K, V> {
+	/// Hashes the bindings of the map in key order,
+	/// so that the hash does not depend on the index of each binding.
+	#[inline]
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.len().hash(state);
+		for item in self.sorted_items() {
+			item.item().as_pair().hash(state)
+		}
+	}
+}
+
+impl<K1, V1, K2, V2> PartialOrd<IndexMap<K2, V2>> for IndexMap<K1, V1>
+where
+	K1: PartialOrd<K2>,
+	V1: PartialOrd<V2>,
+{
+	/// Lexicographically compares the bindings of both maps in key order,
+	/// regardless of their index.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	fn partial_cmp(&self, other: &IndexMap<K2, V2>) -> Option<Ordering> {
+		self.sorted_items().partial_cmp(other.sorted_items())
+	}
+}
+
+impl<K: Ord, V: Ord> Ord for IndexMap<K, V> {
+	/// Lexicographically compares the bindings of both maps in key order,
+	/// regardless of their index.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.sorted_items().cmp(other.sorted_items())
+	}
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for IndexMap<K, V> {
+	/// Builds a map from the given bindings.
+	///
+	/// Duplicate keys follow the semantics of [`IndexMap::insert`]:
+	/// the first index is kept and the last value wins.
+	///
+	/// Computes in **O(n log n)** time.
+	#[inline]
+	fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+		let iter = iter.into_iter();
+		let mut map = Self::with_capacity(iter.size_hint().0);
+		map.build(iter);
+		map
+	}
+}
+
+impl<K: Ord, V> Extend<(K, V)> for IndexMap<K, V> {
+	/// Inserts the given bindings, following the semantics of [`IndexMap::insert`].
+	///
+	/// If the map is empty, the B-Tree is built at once,
+	/// without inserting each binding separately.
+	#[inline]
+	fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+		let iter = iter.into_iter();
+		if self.is_empty() {
+			self.build(iter)
+		} else {
+			// Reserving does not rehash anything, so the whole lower bound
+			// is reserved even if some keys are already in the map.
+			self.reserve(iter.size_hint().0);
+			for (key, value) in iter {
+				self.insert(key, value);
+			}
+		}
+	}
+}
+
+impl<'a, K: 'a + Ord + Copy, V: 'a + Copy> Extend<(&'a K, &'a V)> for IndexMap<K, V> {
+	/// Inserts copies of the given bindings,
+	/// following the semantics of [`IndexMap::insert`].
+	#[inline]
+	fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: I) {
+		self.extend(iter.into_iter().map(|(key, value)| (*key, *value)))
+	}
+}
+
+impl<'a, K, V, Q> std::ops::Index<&'a Q> for IndexMap<K, V>
+where
+	K: Borrow<Q>,
+	Q: ?Sized + Ord,
+{
+	type Output = V;
+
+	fn index(&self, key: &'a Q) -> &V {
+		self.get(key).unwrap()
+	}
+}
+
+impl<'a, K, V, Q> std::ops::IndexMut<&'a Q> for IndexMap<K, V>
+where
+	K: Borrow<Q>,
+	Q: ?Sized + Ord,
+{
+	fn index_mut(&mut self, key: &'a Q) -> &mut V {
+		self.get_mut(key).unwrap()
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for IndexMap<K, V> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+impl<'a, K, V> IntoIterator for &'a IndexMap<K, V> {
+	type Item = (&'a K, &'a V);
+	type IntoIter = Iter<'a, K, V>;
+
+	#[inline]
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl<'a, K, V> IntoIterator for &'a mut IndexMap<K, V> {
+	type Item = (&'a K, &'a mut V);
+	type IntoIter = IterMut<'a, K, V>;
+
+	#[inline]
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter_mut()
+	}
+}
+
+impl<K, V> IntoIterator for IndexMap<K, V> {
+	type Item = (K, V);
+	type IntoIter = IntoIter<K, V>;
+
+	#[inline]
+	fn into_iter(self) -> Self::IntoIter {
+		IntoIter {
+			indexes: self.inner.vec.into_iter(),
+			items: self.inner.items,
+		}
+	}
+}
+
+pub(crate) struct Inner<K, V> {
+	/// Items.
+	items: Slots<item::Ordered<K, V>>,
+
+	/// Ordering array.
+	vec: Vec<Index>,
+}
+
+impl<K: Clone, V: Clone> Clone for Inner<K, V> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Self {
+			items: self.items.clone(),
+			vec: self.vec.clone(),
+		}
+	}
+
+	#[inline]
+	fn clone_from(&mut self, source: &Self) {
+		self.items.clone_from(&source.items);
+		self.vec.clone_from(&source.vec);
+	}
+}
+
+impl<K, V> Inner<K, V> {
+	#[inline]
+	pub fn new() -> Self {
+		Self {
+			items: Slots::new(),
+			vec: Vec::new(),
+		}
+	}
+
+	#[inline]
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self {
+			items: Slots::with_capacity(capacity),
+			vec: Vec::with_capacity(capacity),
+		}
+	}
+
+	/// Returns the number of items the list can hold without reallocating.
+	#[inline]
+	pub fn capacity(&self) -> usize {
+		std::cmp::min(self.items.capacity(), self.vec.capacity())
+	}
+
+	#[inline]
+	pub fn reserve(&mut self, additional: usize) {
+		self.items.reserve(additional);
+		self.vec.reserve(additional)
+	}
+
+	#[inline]
+	pub fn reserve_exact(&mut self, additional: usize) {
+		self.items.reserve_exact(additional);
+		self.vec.reserve_exact(additional)
+	}
+
+	/// Tries to reserve capacity for at least `additional` more items.
+	///
+	/// The vector is reserved first, so that it reports a length overflow.
+	#[inline]
+	pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+		self.vec.try_reserve(additional)?;
+		self.items.try_reserve(additional)
+	}
+
+	/// Shrinks the capacity of the list as much as possible,
+	/// but not below the given capacity.
+	///
+	/// The free slots left between items by removals are kept,
+	/// so the items may not shrink down to the given capacity.
+	#[inline]
+	pub fn shrink_to(&mut self, min_capacity: usize) {
+		let capacity = std::cmp::max(self.vec.len(), min_capacity);
+		if self.items.capacity() > capacity {
+			self.items.shrink_to_fit();
+			self.items.reserve_exact(capacity - self.items.len())
+		}
+
+		self.vec.shrink_to(capacity)
+	}
+
+	/// Insert the given item.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn insert(&mut self, item: Item<K, V>) -> Index {
+		let order = self.vec.len();
+		let index = self.items.insert(item.order(order));
+		self.vec.push(index);
+		index
+	}
+
+	/// Swap remove the item at the given index.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn swap_remove(&mut self, index: Index) -> Item<K, V> {
+		let item = self.items.remove(index);
+		self.vec.swap_remove(item.order);
+
+		if item.order < self.vec.len() {
+			let swapped_index = self.vec[item.order];
+			self.items.get_mut(swapped_index).unwrap().order = item.order;
+		}
+
+		item.unordered()
+	}
+
+	/// Shift remove the item at the given index.
+	///
+	/// Computes in **O(n)** time (average).
+	#[inline]
+	pub fn shift_remove(&mut self, index: Index) -> Item<K, V> {
+		let item = self.items.remove(index);
+		self.vec.remove(item.order);
+		self.renumber(item.order..self.vec.len());
+		item.unordered()
+	}
+
+	/// Inserts the given item at its order,
+	/// which must be a position of `vec` not holding any item yet.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn insert_ordered(&mut self, item: item::Ordered<K, V>) -> Index {
+		let order = item.order;
+		let index = self.items.insert(item);
+		self.vec[order] = index;
+		index
+	}
+
+	/// Removes the given items from `vec`, leaving them in the list.
+	///
+	/// The remaining items keep their relative order and are renumbered.
+	/// The detached items are numbered by their relative order, starting from `0`.
+	/// Returns the number of detached items.
+	///
+	/// Computes in **O(k + n - i)** time (average), where `k` is the number of
+	/// detached items and `i` the lowest order among them.
+	pub fn detach<I: IntoIterator<Item = Index>>(&mut self, indexes: I) -> usize {
+		// Orders are lower than `vec.len()`, so `usize::MAX` marks the detached items.
+		let mut start = self.vec.len();
+		for index in indexes {
+			let item = self.items.get_mut(index).unwrap();
+			start = std::cmp::min(start, item.order);
+			item.order = usize::MAX
+		}
+
+		let mut kept = start;
+		let mut detached = 0;
+		for order in start..self.vec.len() {
+			let index = self.vec[order];
+			let item = self.items.get_mut(index).unwrap();
+			if item.order == usize::MAX {
+				item.order = detached;
+				detached += 1
+			} else {
+				item.order = kept;
+				self.vec[kept] = index;
+				kept += 1
+			}
+		}
+
+		self.vec.truncate(kept);
+		detached
+	}
+
+	/// Updates the order of the items in the given range of `vec`
+	/// to match their position.
+	///
+	/// Computes in **O(k)** time (average), where `k` is the length of the range.
+	#[inline]
+	pub fn renumber(&mut self, range: std::ops::Range<usize>) {
+		for order in range {
+			self.items.get_mut(self.vec[order]).unwrap().order = order
+		}
+	}
+}
+
+/// Renumbers all the items of the inner list when dropped.
+///
+/// Keeps the item orders consistent with the index vector
+/// while it is rearranged by user code that may panic.
+struct RenumberGuard<'a, K, V>(&'a mut Inner<K, V>);
+
+impl<'a, K, V> Drop for RenumberGuard<'a, K, V> {
+	fn drop(&mut self) {
+		let len = self.0.vec.len();
+		self.0.renumber(0..len)
+	}
+}
+
+pub struct Iter<'a, K, V> {
+	inner: &'a Inner<K, V>,
+	indexes: std::slice::Iter<'a, Index>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.inner.items.get(*index).unwrap().as_pair())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.inner.items.get(*index).unwrap().as_pair())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for Iter<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for Iter<'a, K, V> {}
+
+pub struct IterFull<'a, K, V> {
+	inner: &'a Inner<K, V>,
+	indexes: std::slice::Iter<'a, Index>,
+}
+
+impl<'a, K, V> Iterator for IterFull<'a, K, V> {
+	type Item = (usize, &'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.inner.items.get(*index).unwrap().as_full())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterFull<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.inner.items.get(*index).unwrap().as_full())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for IterFull<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for IterFull<'a, K, V> {}
+
+/// Exclusive borrow of the items,
+/// handing out mutable references to distinct items for its whole lifetime.
+pub(crate) type ItemsMut<'a, K, V> = SlotsMut<'a, item::Ordered<K, V>>;
+
+pub struct IterMut<'a, K, V> {
+	items: ItemsMut<'a, K, V>,
+	indexes: std::slice::Iter<'a, Index>,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+	#[inline]
+	fn item_mut(&mut self, index: Index) -> &'a mut item::Ordered<K, V> {
+		// SAFETY: indexes of the ordering array are unique,
+		// and each one is yielded at most once.
+		unsafe { self.items.get(index) }
+	}
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+	type Item = (&'a K, &'a mut V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.cloned()
+			.map(|index| self.item_mut(index).as_pair_mut())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.cloned()
+			.map(|index| self.item_mut(index).as_pair_mut())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for IterMut<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for IterMut<'a, K, V> {}
+
+pub struct Keys<'a, K, V> {
+	inner: &'a Inner<K, V>,
+	indexes: std::slice::Iter<'a, Index>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+	type Item = &'a K;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.inner.items.get(*index).unwrap().as_key())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.inner.items.get(*index).unwrap().as_key())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for Keys<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for Keys<'a, K, V> {}
+
+pub struct Values<'a, K, V> {
+	inner: &'a Inner<K, V>,
+	indexes: std::slice::Iter<'a, Index>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+	type Item = &'a V;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.inner.items.get(*index).unwrap().as_value())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.inner.items.get(*index).unwrap().as_value())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for Values<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for Values<'a, K, V> {}
+
+pub struct ValuesMut<'a, K, V> {
+	items: ItemsMut<'a, K, V>,
+	indexes: std::slice::Iter<'a, Index>,
+}
+
+impl<'a, K, V> ValuesMut<'a, K, V> {
+	#[inline]
+	fn value_mut(&mut self, index: Index) -> &'a mut V {
+		// SAFETY: indexes of the ordering array are unique,
+		// and each one is yielded at most once.
+		unsafe { self.items.get(index) }.as_value_mut()
+	}
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+	type Item = &'a mut V;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.cloned()
+			.map(|index| self.value_mut(index))
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for ValuesMut<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.cloned()
+			.map(|index| self.value_mut(index))
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for ValuesMut<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for ValuesMut<'a, K, V> {}
+
+pub struct IntoIter<K, V> {
+	indexes: std::vec::IntoIter<Index>,
+	items: Slots<item::Ordered<K, V>>,
+}
+
+impl<K, V> IntoIter<K, V> {
+	/// Sorts the remaining bindings with the given comparison function,
+	/// and returns an iterator over them in this order.
+	///
+	/// The sort is stable.
+	///
+	/// Computes in **O(n log n)** time.
+	#[inline]
+	pub fn sorted_by<F>(self, mut cmp: F) -> Self
+	where
+		F: FnMut(&K, &V, &K, &V) -> Ordering,
+	{
+		let items = self.items;
+		let mut indexes: Vec<Index> = self.indexes.collect();
+		indexes.sort_by(|&a, &b| {
+			let a = items.get(a).unwrap();
+			let b = items.get(b).unwrap();
+			cmp(&a.key, &a.value, &b.key, &b.value)
+		});
+
+		Self {
+			indexes: indexes.into_iter(),
+			items,
+		}
+	}
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+	type Item = (K, V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.items.remove(index).into_pair())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.items.remove(index).into_pair())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for IntoIter<K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for IntoIter<K, V> {}
+
+/// Draining iterator over the bindings of a map.
+///
+/// Obtained with [`IndexMap::drain`] or [`IndexMap::drain_range`].
+/// The bindings are detached from the map when this iterator is created,
+/// and moved out of its storage as they are yielded.
+/// Dropping the iterator drops the bindings that were not yielded.
+///
+/// If the iterator is leaked, the bindings that were not yielded
+/// stay in the storage of the map until it is cleared or dropped.
+pub struct Drain<'a, K, V> {
+	/// Indexes of the remaining bindings in the inner list.
+	indexes: std::vec::IntoIter<Index>,
+	items: &'a mut Slots<item::Ordered<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Drain<'a, K, V> {
+	type Item = (K, V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.items.remove(index).into_pair())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for Drain<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.items.remove(index).into_pair())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for Drain<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for Drain<'a, K, V> {}
+
+impl<'a, K, V> Drop for Drain<'a, K, V> {
+	fn drop(&mut self) {
+		for index in &mut self.indexes {
+			self.items.remove(index);
+		}
+	}
+}
+
+pub struct IntoKeys<K, V> {
+	indexes: std::vec::IntoIter<Index>,
+	items: Slots<item::Ordered<K, V>>,
+}
+
+impl<K, V> Iterator for IntoKeys<K, V> {
+	type Item = K;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.items.remove(index).into_key())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<K, V> DoubleEndedIterator for IntoKeys<K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.items.remove(index).into_key())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for IntoKeys<K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for IntoKeys<K, V> {}
+
+pub struct IntoValues<K, V> {
+	indexes: std::vec::IntoIter<Index>,
+	items: Slots<item::Ordered<K, V>>,
+}
+
+impl<K, V> Iterator for IntoValues<K, V> {
+	type Item = V;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.indexes.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.items.remove(index).into_value())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<K, V> DoubleEndedIterator for IntoValues<K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.items.remove(index).into_value())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for IntoValues<K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for IntoValues<K, V> {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Checks the invariants of the map, and panics if one is broken.
+	fn validate<K: Ord, V>(map: &IndexMap<K, V>) {
+		assert_eq!(node::validate(&map.nodes, map.root), map.nodes.len());
+		assert_eq!(map.inner.items.len(), map.len());
+		for (order, &index) in map.inner.vec.iter().enumerate() {
+			assert_eq!(map.inner.items.get(index).unwrap().order, order)
+		}
+
+		let indexes: Vec<_> = sorted::Indexes::new(map.btree()).collect();
+		assert_eq!(indexes.len(), map.len());
+		for pair in indexes.windows(2) {
+			let item = |index| &map.inner.items.get(index).unwrap().key;
+			assert!(item(pair[0]) < item(pair[1]))
+		}
+	}
+
+	/// Builds a map by inserting the keys `0..len` one by one,
+	/// shuffled by multiplying them with `step`, coprime with `len`.
+	/// Each key is bound to its double.
+	///
+	/// Returns the keys in insertion order, with the map.
+	fn shuffled(len: usize, step: usize) -> (Vec<usize>, IndexMap<usize, usize>) {
+		let keys: Vec<_> = (0..len).map(|k| k * step % len).collect();
+		let mut map = IndexMap::new();
+		for &k in &keys {
+			map.insert(k, k * 2);
+		}
+
+		(keys, map)
+	}
+
+	/// Checks that the map holds the given keys in this insertion order,
+	/// each bound to its double, and that it ranks them correctly.
+	fn check_bindings(map: &IndexMap<usize, usize>, keys: &[usize]) {
+		validate(map);
+		assert!(map.keys().eq(keys.iter()));
+		let mut sorted = keys.to_vec();
+		sorted.sort_unstable();
+		assert!(map.keys_sorted().eq(sorted.iter()));
+		for (rank, key) in sorted.iter().enumerate() {
+			assert_eq!(map.rank_of(key), Some(rank));
+		}
+		for (i, key) in keys.iter().enumerate() {
+			assert_eq!(map.get_full(key), Some((i, key, &(key * 2))));
+		}
+	}
+
+	#[test]
+	fn it_works() {
+		let mut map = IndexMap::new();
+		assert_eq!(map.is_empty(), true);
+		map.insert(1, ());
+		map.insert(1, ());
+		assert_eq!(map.len(), 1);
+		assert!(map.get(&1).is_some());
+		assert_eq!(map.is_empty(), false);
+	}
+
+	#[test]
+	fn new() {
+		let map = IndexMap::<String, String>::new();
+		println!("{:?}", map);
+		assert_eq!(map.capacity(), 0);
+		assert_eq!(map.len(), 0);
+		assert_eq!(map.is_empty(), true);
+	}
+
+	#[test]
+	fn insert() {
+		let insert = [0, 4, 2, 12, 8, 7, 11, 5];
+		let not_present = [1, 3, 6, 9, 10];
+		let mut map = IndexMap::with_capacity(insert.len());
+
+		for (i, elt) in insert.into_iter().enumerate() {
+			assert_eq!(map.len(), i);
+			map.insert(elt, elt);
+			assert_eq!(map.len(), i + 1);
+			assert_eq!(map.get(&elt), Some(&elt));
+			assert_eq!(map[&elt], elt);
+		}
+		println!("{:?}", map);
+
+		for &elt in &not_present {
+			assert!(map.get(&elt).is_none());
+		}
+	}
+
+	#[test]
+	fn insert_full() {
+		let insert = vec![9, 2, 7, 1, 4, 6, 13];
+		let present = vec![1, 6, 2];
+		let mut map = IndexMap::with_capacity(insert.len());
+
+		for (i, elt) in insert.into_iter().enumerate() {
+			assert_eq!(map.len(), i);
+			let (index, existing) = map.insert_full(elt, elt);
+			assert_eq!(existing, None);
+			assert_eq!(Some(index), map.get_full(&elt).map(|x| x.0));
+			assert_eq!(map.len(), i + 1);
+		}
+
+		let len = map.len();
+		for elt in present {
+			let (index, existing) = map.insert_full(elt, elt);
+			assert_eq!(existing, Some(elt));
+			assert_eq!(Some(index), map.get_full(&elt).map(|x| x.0));
+			assert_eq!(map.len(), len);
+		}
+	}
+
+	#[test]
+	fn full_indexes_after_removal() {
+		let mut map = IndexMap::new();
+		for k in 0..10 {
+			map.insert(k, k);
+		}
+		map.swap_remove(&2);
+		map.shift_remove(&0);
+		for (i, key) in [1, 9, 3, 4, 5, 6, 7, 8].iter().enumerate() {
+			assert_eq!(map.get_index(i), Some((key, key)));
+			assert_eq!(map.get_full(key), Some((i, key, key)));
+			assert_eq!(map.insert_full(*key, *key), (i, Some(*key)));
+		}
+		assert_eq!(map.insert_full(0, 0), (8, None));
+	}
+
+	#[test]
+	fn swap_remove_last() {
+		let mut map = IndexMap::new();
+		for k in 0..4 {
+			map.insert(k, k);
+		}
+		assert_eq!(map.swap_remove(&3), Some(3));
+		assert_eq!(map.swap_remove(&0), Some(0));
+		assert_eq!(map.get_index(0), Some((&2, &2)));
+		assert_eq!(map.get_full(&1), Some((1, &1, &1)));
+		assert_eq!(map.len(), 2);
+	}
+
+	#[test]
+	fn insert_2() {
+		let mut map = IndexMap::with_capacity(16);
+
+		let mut keys = vec![];
+		keys.extend(0..16);
+		keys.extend(128..267);
+
+		for &i in &keys {
+			let old_map = map.clone();
+			map.insert(i, ());
+			for key in old_map.keys() {
+				assert!(map.get(key).is_some(), "did not find {} in map", key);
+			}
+		}
+
+		for &i in &keys {
+			assert!(map.get(&i).is_some(), "did not find {}", i);
+		}
+	}
+
+	#[test]
+	fn insert_order() {
+		let insert = [0, 4, 2, 12, 8, 7, 11, 5, 3, 17, 19, 22, 23];
+		let mut map = IndexMap::new();
+
+		for &elt in &insert {
+			map.insert(elt, ());
+		}
+
+		assert_eq!(map.keys().count(), map.len());
+		assert_eq!(map.keys().count(), insert.len());
+		for (a, b) in insert.iter().zip(map.keys()) {
+			assert_eq!(a, b);
+		}
+		for (i, k) in (0..insert.len()).zip(map.keys()) {
+			assert_eq!(map.get_index(i).unwrap().0, k);
+		}
+	}
+
+	#[test]
+	fn iter_mut() {
+		let mut map = IndexMap::new();
+		for i in [5, 3, 8, 1] {
+			map.insert(i, i);
+		}
+
+		for (key, value) in map.iter_mut() {
+			*value += key * 10;
+		}
+		for value in map.values_mut().rev() {
+			*value += 1;
+		}
+
+		assert!(map.keys().eq([5, 3, 8, 1].iter()));
+		assert!(map.values().eq([56, 34, 89, 12].iter()));
+		assert!(map.clone().into_keys().rev().eq([1, 8, 3, 5]));
+		assert!(map.into_values().eq([56, 34, 89, 12]));
+	}
+
+	/// Keeps every yielded reference alive while the iteration goes on.
+	///
+	/// Meant to be run under Miri (`cargo +nightly miri test iter_mut`)
+	/// to check that yielding an item does not invalidate the previous ones.
+	#[test]
+	fn iter_mut_aliasing() {
+		let mut map: IndexMap<_, _> = (0..20).map(|i| (i * 7 % 20, i)).collect();
+
+		let mut entries: Vec<_> = map.iter_mut().collect();
+		let (_, last) = entries.pop().unwrap();
+		*last += 100;
+		for (_, value) in entries {
+			*value += 100;
+		}
+
+		let values: Vec<_> = map.values_mut().rev().collect();
+		for value in values {
+			*value *= 2;
+		}
+
+		assert!(map
+			.values()
+			.eq((100..120).map(|i| i * 2).collect::<Vec<_>>().iter()));
+	}
+
+	/// Meant to be run under Miri (`cargo +nightly miri test range_mut`)
+	/// to check that yielding an item does not invalidate the previous ones.
+	#[test]
+	fn range_mut_aliasing() {
+		let mut map: IndexMap<_, _> = (0..20).map(|i| (i * 7 % 20, i)).collect();
+
+		let mut range = map.range_mut(5..15);
+		let (_, first, first_value) = range.next().unwrap();
+		let (_, last, last_value) = range.next_back().unwrap();
+		let entries: Vec<_> = range.rev().collect();
+		*first_value = first * 10;
+		*last_value = last * 10;
+		for (_, key, value) in entries {
+			*value = key * 10;
+		}
+
+		for (key, value) in &map {
+			if (5..15).contains(key) {
+				assert_eq!(*value, key * 10);
+			} else {
+				assert_eq!(value * 7 % 20, *key);
+			}
+		}
+	}
+
+	// #[test]
+	// fn grow() {
+	//     let insert = [0, 4, 2, 12, 8, 7, 11];
+	//     let not_present = [1, 3, 6, 9, 10];
+	//     let mut map = IndexMap::with_capacity(insert.len());
+
+	//     for (i, &elt) in enumerate(&insert) {
+	//         assert_eq!(map.len(), i);
+	//         map.insert(elt, elt);
+	//         assert_eq!(map.len(), i + 1);
+	//         assert_eq!(map.get(&elt), Some(&elt));
+	//         assert_eq!(map[&elt], elt);
+	//     }
+
+	//     println!("{:?}", map);
+	//     for &elt in &insert {
+	//         map.insert(elt * 10, elt);
+	//     }
+	//     for &elt in &insert {
+	//         map.insert(elt * 100, elt);
+	//     }
+	//     for (i, &elt) in insert.iter().cycle().enumerate().take(100) {
+	//         map.insert(elt * 100 + i as i32, elt);
+	//     }
+	//     println!("{:?}", map);
+	//     for &elt in &not_present {
+	//         assert!(map.get(&elt).is_none());
+	//     }
+	// }
+
+	#[test]
+	fn reserve() {
+		let mut map = IndexMap::<usize, usize>::new();
+		assert_eq!(map.capacity(), 0);
+		map.reserve(100);
+		let capacity = map.capacity();
+		assert!(capacity >= 100);
+		for i in 0..capacity {
+			assert_eq!(map.len(), i);
+			map.insert(i, i * i);
+			assert_eq!(map.len(), i + 1);
+			assert_eq!(map.capacity(), capacity);
+			assert_eq!(map.get(&i), Some(&(i * i)));
+		}
+		map.insert(capacity, usize::MAX);
+		assert_eq!(map.len(), capacity + 1);
+		assert!(map.capacity() > capacity);
+		assert_eq!(map.get(&capacity), Some(&usize::MAX));
+	}
+
+	#[test]
+	fn try_reserve() {
+		let mut map = IndexMap::<usize, usize>::new();
+		assert!(map.try_reserve(usize::MAX).is_err());
+		assert!(map.try_reserve(usize::MAX / 2).is_err());
+		assert!(map.is_empty());
+		assert!(map.try_reserve(10).is_ok());
+		assert!(map.capacity() >= 10);
+
+		map.insert(0, 0);
+		map.reserve_exact(20);
+		assert!(map.capacity() >= 21);
+		map.shrink_to(5);
+		assert!(map.capacity() >= 5);
+		assert_eq!(map.get(&0), Some(&0));
+		assert!(map.try_reserve(usize::MAX).is_err());
+		assert_eq!(map.len(), 1);
+	}
+
+	#[test]
+	#[should_panic(expected = "capacity overflow")]
+	fn reserve_overflow() {
+		let mut map = IndexMap::new();
+		map.insert(0, 0);
+		map.reserve(usize::MAX)
+	}
+
+	#[test]
+	fn capacity_after_removals() {
+		let mut map = IndexMap::with_capacity(1000);
+		let capacity = map.capacity();
+		let capacities = |map: &IndexMap<usize, usize>| {
+			(
+				map.nodes.capacity(),
+				map.inner.items.capacity(),
+				map.inner.vec.capacity(),
+			)
+		};
+		let expected = capacities(&map);
+
+		for k in 0..capacity {
+			map.insert(k, k);
+		}
+		// Removing most bindings leaves nodes holding about as few items as possible.
+		for k in (0..capacity).filter(|k| k % 5 != 0) {
+			map.swap_remove(&k);
+		}
+		validate(&map);
+		assert!(map.nodes.len() > node::nodes_for(map.len()) * 9 / 10);
+
+		for k in 0..capacity - map.len() {
+			map.insert(capacity + k, k);
+		}
+		validate(&map);
+		assert_eq!(map.len(), capacity);
+		assert_eq!(capacities(&map), expected);
+	}
+
+	#[test]
+	fn shrink_to_fit() {
+		let mut map = IndexMap::<usize, usize>::new();
+		assert_eq!(map.capacity(), 0);
+		for i in 0..100 {
+			assert_eq!(map.len(), i);
+			map.insert(i, i * i);
+			assert_eq!(map.len(), i + 1);
+			assert!(map.capacity() > i);
+			assert_eq!(map.get(&i), Some(&(i * i)));
+			map.shrink_to_fit();
+			assert_eq!(map.len(), i + 1);
+			assert_eq!(map.capacity(), i + 1);
+			assert_eq!(map.get(&i), Some(&(i * i)));
+		}
+	}
+
+	#[test]
+	fn remove() {
+		let insert = [0, 4, 2, 12, 8, 7, 11, 5, 3, 17, 19, 22, 23];
+		let mut map = IndexMap::new();
+
+		for &elt in &insert {
+			map.insert(elt, elt);
+		}
+
+		assert_eq!(map.keys().count(), map.len());
+		assert_eq!(map.keys().count(), insert.len());
+		for (a, b) in insert.iter().zip(map.keys()) {
+			assert_eq!(a, b);
+		}
+
+		let remove_fail = [99, 77];
+		let remove = [4, 12, 8, 7];
+
+		for &key in &remove_fail {
+			assert!(map.swap_remove_full(&key).is_none());
+		}
+		for &key in &remove {
+			let index = map.get_full(&key).unwrap().0;
+			assert_eq!(map.swap_remove_full(&key), Some((index, key, key)));
+		}
+
+		for key in &insert {
+			assert_eq!(map.get(key).is_some(), !remove.contains(key));
+		}
+		assert_eq!(map.len(), insert.len() - remove.len());
+		assert_eq!(map.keys().count(), insert.len() - remove.len());
+	}
+
+	#[test]
+	fn remove_to_empty() {
+		let mut map: IndexMap<_, _> = [(0, 0), (4, 4), (5, 5)].into_iter().collect();
+		map.swap_remove(&5).unwrap();
+		map.swap_remove(&4).unwrap();
+		map.swap_remove(&0).unwrap();
+		assert!(map.is_empty());
+	}
+
+	#[test]
+	fn swap_remove_index() {
+		let insert = [0, 4, 2, 12, 8, 7, 11, 5, 3, 17, 19, 22, 23];
+		let mut map = IndexMap::new();
+
+		for &elt in &insert {
+			map.insert(elt, elt * 2);
+		}
+
+		let mut vector = insert.to_vec();
+		let remove_sequence = &[3, 3, 10, 4, 5, 4, 3, 0, 1];
+
+		// check that the same swap remove sequence on vec and map
+		// have the same result.
+		for &rm in remove_sequence {
+			let out_vec = vector.swap_remove(rm);
+			let (out_map, _) = map.swap_remove_index(rm).unwrap();
+			assert_eq!(out_vec, out_map);
+		}
+		assert_eq!(vector.len(), map.len());
+		for (a, b) in vector.iter().zip(map.keys()) {
+			assert_eq!(a, b);
+		}
+	}
+
+	#[test]
+	fn shift_remove_index() {
+		let insert = [0, 4, 2, 12, 8, 7, 11, 5, 3, 17, 19, 22, 23];
+		let mut map: IndexMap<_, _> = insert.iter().map(|&elt| (elt, elt * 2)).collect();
+
+		let mut vector = insert.to_vec();
+		for &rm in &[3, 3, 10, 4, 5, 4, 3, 0, 1] {
+			let out_vec = vector.remove(rm);
+			assert_eq!(map.shift_remove_index(rm), Some((out_vec, out_vec * 2)));
+		}
+		assert_eq!(map.shift_remove_index(vector.len()), None);
+		assert!(map.keys().eq(vector.iter()));
+		for (i, key) in vector.iter().enumerate() {
+			assert_eq!(map.shift_remove_full(key), Some((0, *key, key * 2)));
+			assert_eq!(map.len(), vector.len() - i - 1);
+		}
+	}
+
+	#[test]
+	fn sorted() {
+		let insert = [7, 19, 3, 12, 0, 25, 14, 8, 1, 30, 22, 5, 17, 10, 28, 2, 11];
+		let mut map: IndexMap<_, _> = insert.iter().map(|&k| (k, k * 2)).collect();
+		for k in 100..200 {
+			map.insert(k, k * 2);
+		}
+
+		let mut sorted: Vec<_> = map.keys().copied().collect();
+		sorted.sort();
+		assert!(map.keys_sorted().eq(sorted.iter()));
+		assert!(map.keys_sorted().rev().eq(sorted.iter().rev()));
+		assert_eq!(map.iter_sorted().len(), map.len());
+		for (i, key, value) in map.iter_sorted() {
+			assert_eq!(map.get_index(i), Some((key, value)));
+		}
+
+		let range: Vec<_> = map.range(4..=17).map(|(_, k, _)| *k).collect();
+		assert_eq!(range, [5, 7, 8, 10, 11, 12, 14, 17]);
+		let range: Vec<_> = map.range(..3).rev().map(|(_, k, _)| *k).collect();
+		assert_eq!(range, [2, 1, 0]);
+		let range: Vec<_> = map.range(150..).map(|(_, k, _)| *k).collect();
+		assert!(range.into_iter().eq(150..200));
+		assert_eq!(map.range(31..100).next(), None);
+		assert_eq!(map.range(18..19).next(), None);
+		assert_eq!(
+			map.range((Bound::Excluded(19), Bound::Excluded(22))).next(),
+			None
+		);
+
+		let mut range = map.range(10..=14);
+		assert_eq!(range.next().map(|(_, k, _)| *k), Some(10));
+		assert_eq!(range.next_back().map(|(_, k, _)| *k), Some(14));
+		assert_eq!(range.next().map(|(_, k, _)| *k), Some(11));
+		assert_eq!(range.next_back().map(|(_, k, _)| *k), Some(12));
+		assert_eq!(range.next(), None);
+		assert_eq!(range.next_back(), None);
+
+		for (_, key, value) in map.range_mut(100..110) {
+			*value = *key;
+		}
+		assert_eq!(map.get(&105), Some(&105));
+		assert_eq!(map.get(&110), Some(&220));
+
+		// Every yielded reference stays alive, see `iter_mut_aliasing`.
+		let values: Vec<_> = map.range_mut(100..110).rev().map(|(_, _, v)| v).collect();
+		for value in values {
+			*value += 1;
+		}
+		assert_eq!(map.get(&100), Some(&101));
+		assert_eq!(map.get(&109), Some(&110));
+	}
+
+	#[test]
+	fn rank() {
+		let mut map: IndexMap<_, _> = (0..50).map(|k| (k * 3, k)).collect();
+		for k in (150..300).rev() {
+			map.insert(k * 3, k);
+		}
+		for k in 300..320 {
+			*map.entry(k * 3).or_default() += k;
+		}
+		for k in (0..320).step_by(7) {
+			map.swap_remove(&(k * 3));
+		}
+		for k in (1..320).step_by(11) {
+			map.shift_remove(&(k * 3));
+		}
+
+		for (rank, &key) in map.keys_sorted().enumerate() {
+			assert_eq!(map.rank_of(&key), Some(rank));
+			assert_eq!(map.rank_of(&(key + 1)), None);
+			assert_eq!(map.get_by_rank(rank).map(|(_, k, _)| *k), Some(key));
+		}
+		assert_eq!(map.get_by_rank(map.len()), None);
+
+		let sorted: Vec<_> = map.keys_sorted().copied().collect();
+		for (start, end) in [(0, 30), (31, 500), (450, 451), (600, 1000), (10, 5)] {
+			let expected = sorted.iter().filter(|&&k| start <= k && k < end).count();
+			assert_eq!(map.count_range(start..end), expected);
+			let expected = sorted.iter().filter(|&&k| start < k && k <= end).count();
+			assert_eq!(
+				map.count_range((Bound::Excluded(start), Bound::Included(end))),
+				expected
+			);
+		}
+		assert_eq!(map.count_range(..), map.len());
+
+		assert_eq!(
+			map.quantile(0.0).map(|(_, k, _)| *k),
+			sorted.first().copied()
+		);
+		assert_eq!(
+			map.quantile(1.0).map(|(_, k, _)| *k),
+			sorted.last().copied()
+		);
+		assert_eq!(
+			map.quantile(2.0).map(|(_, k, _)| *k),
+			sorted.last().copied()
+		);
+		let median = sorted[((sorted.len() - 1) as f64 * 0.5).round() as usize];
+		assert_eq!(map.quantile(0.5).map(|(_, k, _)| *k), Some(median));
+		assert_eq!(IndexMap::<i32, i32>::new().quantile(0.5), None);
+	}
+
+	#[test]
+	fn pop_by_key() {
+		let insert = [5, 3, 9, 1, 7, 0, 8];
+		let mut map: IndexMap<_, _> = insert.iter().map(|&k| (k, k * 10)).collect();
+		assert_eq!(map.first_by_key(), Some((5, &0, &0)));
+		assert_eq!(map.last_by_key(), Some((2, &9, &90)));
+
+		assert_eq!(map.shift_pop_first_by_key(), Some((5, 0, 0)));
+		assert_eq!(map.shift_pop_first_by_key(), Some((3, 1, 10)));
+		assert!(map.keys().eq([5, 3, 9, 7, 8].iter()));
+
+		assert_eq!(map.pop_last_by_key(), Some((2, 9, 90)));
+		assert!(map.keys().eq([5, 3, 8, 7].iter()));
+		assert_eq!(map.swap_pop_first_by_key(), Some((1, 3, 30)));
+		assert!(map.keys().eq([5, 7, 8].iter()));
+		assert_eq!(map.shift_pop_last_by_key(), Some((2, 8, 80)));
+		assert_eq!(map.pop_first_by_key(), Some((0, 5, 50)));
+		assert_eq!(map.pop_first_by_key(), Some((0, 7, 70)));
+		assert_eq!(map.pop_first_by_key(), None);
+		assert_eq!(map.last_by_key(), None);
+
+		let mut map: IndexMap<_, _> = (0..100).rev().map(|k| (k, ())).collect();
+		for k in 0..100 {
+			assert_eq!(map.pop_first_by_key().map(|(_, k, _)| k), Some(k));
+		}
+		assert!(map.is_empty());
+	}
+
+	#[test]
+	fn retain() {
+		let mut map: IndexMap<_, _> = (0..200).rev().map(|k| (k, k)).collect();
+		map.retain(|k, v| {
+			*v *= 2;
+			k % 3 != 0
+		});
+
+		let expected: Vec<_> = (0..200).rev().filter(|k| k % 3 != 0).collect();
+		assert!(map.keys().eq(expected.iter()));
+		for (i, key) in expected.iter().enumerate() {
+			assert_eq!(map.get_full(key), Some((i, key, &(key * 2))));
+		}
+		assert!(map.keys_sorted().eq(expected.iter().rev()));
+
+		map.retain(|k, _| *k != 100);
+		assert_eq!(map.len(), expected.len() - 1);
+		assert_eq!(map.get(&100), None);
+		let index = expected.iter().position(|&k| k == 98).unwrap() - 1;
+		assert_eq!(map.get_full(&98), Some((index, &98, &196)));
+
+		map.retain(|_, _| false);
+		assert!(map.is_empty());
+		assert_eq!(map.first_by_key(), None);
+		map.insert(3, 3);
+		assert_eq!(map.get(&3), Some(&3));
+	}
+
+	#[test]
+	fn drain() {
+		let mut map: IndexMap<_, _> = (0..100).map(|k| (k * 7 % 100, k)).collect();
+		let keys: Vec<_> = map.keys().copied().collect();
+
+		let drained: Vec<_> = map.drain(10..20).map(|(k, _)| k).collect();
+		assert_eq!(drained, keys[10..20]);
+		assert_eq!(map.len(), 90);
+		for (i, key) in keys[..10].iter().chain(&keys[20..]).enumerate() {
+			assert_eq!(map.get_full(key).map(|(i, _, _)| i), Some(i));
+		}
+		for key in &drained {
+			assert_eq!(map.get(key), None);
+		}
+
+		let tail = map.split_off(80);
+		assert!(tail.keys().eq(keys[90..].iter()));
+		assert_eq!(tail.get_full(&keys[95]).map(|(i, _, _)| i), Some(5));
+		assert_eq!(map.len(), 80);
+
+		map.truncate(75);
+		assert!(map.keys().eq(keys[..10].iter().chain(&keys[20..85])));
+		map.truncate(100);
+		assert_eq!(map.len(), 75);
+
+		assert_eq!(map.drain(..).len(), 75);
+		assert!(map.is_empty());
+		map.insert(1, 1);
+		assert!(map.drain(0..0).next().is_none());
+		map.clear();
+		assert!(map.is_empty());
+		assert_eq!(map.get(&1), None);
+		assert_eq!(map.keys_sorted().next(), None);
+	}
+
+	#[test]
+	fn drain_partially_consumed() {
+		let value = std::rc::Rc::new(());
+		let mut map: IndexMap<_, _> = (0..100).map(|k| (k * 7 % 100, value.clone())).collect();
+		let keys: Vec<_> = map.keys().copied().collect();
+
+		let mut drain = map.drain(20..60);
+		assert_eq!(drain.len(), 40);
+		assert_eq!(drain.next().map(|(k, _)| k), Some(keys[20]));
+		assert_eq!(drain.next_back().map(|(k, _)| k), Some(keys[59]));
+		assert_eq!(drain.len(), 38);
+		drop(drain);
+		validate(&map);
+		assert!(map.keys().eq(keys[..20].iter().chain(&keys[60..])));
+		assert_eq!(std::rc::Rc::strong_count(&value), 61);
+
+		let mut drain = map.drain_range(10..90);
+		assert_eq!(drain.next().map(|(k, _)| k), Some(10));
+		drop(drain);
+		validate(&map);
+		assert!(map.keys_sorted().all(|&k| !(10..90).contains(&k)));
+		assert_eq!(std::rc::Rc::strong_count(&value), map.len() + 1);
+
+		map.drain(..);
+		assert!(map.is_empty());
+		assert_eq!(std::rc::Rc::strong_count(&value), 1);
+	}
+
+	#[test]
+	fn drain_leaked() {
+		let (keys, mut map) = shuffled(20, 7);
+		std::mem::forget(map.drain(5..10));
+		std::mem::forget(map.drain_range(0..3));
+		let mut expected: Vec<_> = keys[..5]
+			.iter()
+			.chain(&keys[10..])
+			.copied()
+			.filter(|&k| k >= 3)
+			.collect();
+		assert_eq!(map.len(), expected.len());
+
+		assert_eq!(map.insert_full(100, 200), (expected.len(), None));
+		expected.push(100);
+		assert_eq!(map.get_index(expected.len() - 1), Some((&100, &200)));
+		assert_eq!(map.get_index(expected.len()), None);
+		assert!(map.keys().eq(expected.iter()));
+		for (i, key) in expected.iter().enumerate() {
+			assert_eq!(map.get_index_of(key), Some(i))
+		}
+
+		// The leaked bindings are released by `clear`.
+		map.clear();
+		validate(&map);
+	}
+
+	#[test]
+	fn sort() {
+		let mut map: IndexMap<_, _> = (0..50).map(|k| (k * 13 % 50, k)).collect();
+		map.sort_keys();
+		assert!(map.keys().copied().eq(0..50));
+		for k in 0..50 {
+			assert_eq!(map.get_full(&k).map(|(i, _, _)| i), Some(k));
+		}
+
+		map.sort_by(|_, v1, _, v2| v1.cmp(v2));
+		assert!(map.values().copied().eq(0..50));
+		assert_eq!(map.get_full(&13), Some((1, &13, &1)));
+
+		map.sort_unstable_by(|k1, _, k2, _| k2.cmp(k1));
+		assert!(map.keys().copied().eq((0..50).rev()));
+
+		map.reverse();
+		assert!(map.keys().copied().eq(0..50));
+		assert_eq!(map.get_full(&49).map(|(i, _, _)| i), Some(49));
+
+		let map: IndexMap<_, _> = [(1, 'b'), (2, 'a'), (3, 'b'), (4, 'a')]
+			.into_iter()
+			.collect();
+		let sorted: Vec<_> = map
+			.into_iter()
+			.sorted_by(|_, v1, _, v2| v1.cmp(v2))
+			.collect();
+		assert_eq!(sorted, [(2, 'a'), (4, 'a'), (1, 'b'), (3, 'b')]);
+	}
+
+	#[test]
+	fn sort_by_panic() {
+		let mut map: IndexMap<_, _> = (0..50).map(|k| (k, k * 7 % 50)).collect();
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			let mut count = 0;
+			map.sort_by(|_, v1, _, v2| {
+				count += 1;
+				assert!(count < 100, "comparator panic");
+				v1.cmp(v2)
+			})
+		}));
+
+		assert!(result.is_err());
+		assert_eq!(map.len(), 50);
+		for (i, (key, _)) in map.iter().enumerate() {
+			assert_eq!(map.get_full(key).map(|(i, _, _)| i), Some(i));
+		}
+	}
+
+	#[test]
+	fn move_index() {
+		let mut map: IndexMap<_, _> = "abcdef".chars().map(|c| (c, c as u32)).collect();
+		map.move_index(1, 4);
+		assert!(map.keys().eq("acdebf".chars().collect::<Vec<_>>().iter()));
+		map.move_index(5, 0);
+		assert!(map.keys().eq("facdeb".chars().collect::<Vec<_>>().iter()));
+		map.swap_indices(0, 5);
+		assert!(map.keys().eq("bacdef".chars().collect::<Vec<_>>().iter()));
+		for (i, key) in "bacdef".chars().enumerate() {
+			assert_eq!(map.get_full(&key).map(|(i, _, _)| i), Some(i));
+		}
+
+		assert_eq!(map.shift_insert(2, 'x', 0), None);
+		assert_eq!(map.shift_insert(0, 'e', 1), Some('e' as u32));
+		assert!(map.keys().eq("ebaxcdf".chars().collect::<Vec<_>>().iter()));
+		assert_eq!(map.get_full(&'e'), Some((0, &'e', &1)));
+
+		assert_eq!(map.insert_before(&'c', 'y', 2), Ok((4, None)));
+		assert_eq!(map.insert_after(&'c', 'b', 3), Ok((5, Some('b' as u32))));
+		assert_eq!(map.insert_before(&'z', 'w', 4), Err(('w', 4)));
+		assert_eq!(map.insert_after(&'f', 'e', 5), Ok((7, Some(1))));
+		assert!(map.keys().eq("axycbdfe".chars().collect::<Vec<_>>().iter()));
+		for (i, key) in "axycbdfe".chars().enumerate() {
+			assert_eq!(map.get_full(&key).map(|(i, _, _)| i), Some(i));
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "index 6 out of bounds (len is 6)")]
+	fn swap_indices_out_of_bounds() {
+		let mut map: IndexMap<_, _> = "abcdef".chars().map(|c| (c, ())).collect();
+		map.swap_indices(0, 6)
+	}
+
+	#[test]
+	fn permute() {
+		let mut map: IndexMap<_, _> = "abcde".chars().map(|c| (c, ())).collect();
+		map.reserve(100);
+		let capacity = map.capacity();
+		assert_eq!(map.permute(&[4, 2, 0, 1, 3]), Ok(()));
+		assert!(map.keys().eq("ecabd".chars().collect::<Vec<_>>().iter()));
+		assert_eq!(map.capacity(), capacity);
+		for (i, key) in "ecabd".chars().enumerate() {
+			assert_eq!(map.get_full(&key).map(|(i, _, _)| i), Some(i));
+		}
+
+		assert_eq!(
+			map.permute(&[0, 1]),
+			Err(PermutationError::Length {
+				expected: 5,
+				found: 2
+			})
+		);
+		assert_eq!(
+			map.permute(&[0, 1, 2, 3, 5]),
+			Err(PermutationError::OutOfBounds(5))
+		);
+		assert_eq!(
+			map.permute(&[0, 1, 2, 1, 4]),
+			Err(PermutationError::Duplicate(1))
+		);
+		assert!(map.keys().eq("ecabd".chars().collect::<Vec<_>>().iter()));
+
+		map.rotate_left(2);
+		assert!(map.keys().eq("abdec".chars().collect::<Vec<_>>().iter()));
+		map.rotate_right(1);
+		assert!(map.keys().eq("cabde".chars().collect::<Vec<_>>().iter()));
+		assert_eq!(map.get_full(&'e').map(|(i, _, _)| i), Some(4));
+	}
+
+	#[test]
+	fn split_off_key_and_append() {
+		let (keys, mut map) = shuffled(200, 37);
+		let head_keys: Vec<_> = keys.iter().copied().filter(|&k| k < 150).collect();
+		let tail_keys: Vec<_> = keys.iter().copied().filter(|&k| k >= 150).collect();
+
+		let mut tail = map.split_off_key(&150);
+		check_bindings(&map, &head_keys);
+		check_bindings(&tail, &tail_keys);
+
+		assert!(map.split_off_key(&500).is_empty());
+		let capacity = map.capacity();
+		let mut head = map.split_off_key(&0);
+		assert!(map.is_empty());
+		assert!(map.capacity() >= capacity);
+		assert!(head.capacity() >= capacity);
+		check_bindings(&head, &head_keys);
+
+		tail.insert(10, 1000);
+		head.append(&mut tail);
+		validate(&head);
+		assert!(tail.is_empty());
+		assert_eq!(head.get(&10), Some(&1000));
+		head.insert(10, 20);
+		check_bindings(&head, &[head_keys, tail_keys].concat());
+
+		let mut small: IndexMap<_, _> = [(500, 0), (7, 7)].into_iter().collect();
+		head.append(&mut small);
+		validate(&head);
+		assert_eq!(head.len(), 201);
+		assert_eq!(head.get(&7), Some(&7));
+		assert_eq!(head.get_full(&500), Some((200, &500, &0)));
 	}
 
-	#[inline]
-	fn last(mut self) -> Option<Self::Item> {
-		self.next_back()
+	#[test]
+	fn split_and_join() {
+		for at in [1, 7, 8, 9, 64, 333, 500, 999] {
+			let (keys, mut map) = shuffled(1000, 379);
+			let mut tail = map.split_off_key(&at);
+			let mut head_keys: Vec<_> = keys.iter().copied().filter(|&k| k < at).collect();
+			let mut tail_keys: Vec<_> = keys.iter().copied().filter(|&k| k >= at).collect();
+			check_bindings(&map, &head_keys);
+			check_bindings(&tail, &tail_keys);
+
+			tail.insert(1000 + at, (1000 + at) * 2);
+			tail_keys.push(1000 + at);
+			map.shift_remove(&0);
+			head_keys.retain(|&k| k != 0);
+
+			let mut head = map.clone();
+			head.append(&mut tail.clone());
+			check_bindings(&head, &[head_keys.clone(), tail_keys.clone()].concat());
+			tail.append(&mut map);
+			check_bindings(&tail, &[tail_keys, head_keys].concat());
+		}
 	}
-}
 
-impl<K, V> DoubleEndedIterator for IntoValues<K, V> {
-	#[inline]
-	fn next_back(&mut self) -> Option<Self::Item> {
-		self.indexes
-			.next_back()
-			.map(|index| self.items.remove(index).into_value())
+	#[test]
+	fn split_and_join_edge_cases() {
+		let mut empty: IndexMap<usize, usize> = IndexMap::new();
+		assert!(empty.split_off_key(&0).is_empty());
+		empty.append(&mut IndexMap::new());
+		assert!(empty.is_empty());
+
+		// Appending to an empty map, then an empty map.
+		let (keys, mut map) = shuffled(100, 13);
+		empty.append(&mut map);
+		assert!(map.is_empty());
+		check_bindings(&empty, &keys);
+		empty.append(&mut map);
+		check_bindings(&empty, &keys);
+
+		// Splitting at the first and last keys, and joining a single binding back.
+		let mut map = empty;
+		let last = map.split_off_key(&99);
+		check_bindings(&last, &[99]);
+		let all = map.split_off_key(&0);
+		assert!(map.is_empty());
+		map = all;
+		map.append(&mut last.clone());
+		let mut expected: Vec<_> = keys.iter().copied().filter(|&k| k != 99).collect();
+		expected.push(99);
+		check_bindings(&map, &expected);
+
+		// Joining trees of very different heights, on both sides.
+		let (keys, mut map) = shuffled(1000, 379);
+		let mut low: IndexMap<_, _> = [(2000, 4000)].into_iter().collect();
+		low.append(&mut map);
+		check_bindings(&low, &[&[2000][..], &keys[..]].concat());
+		let mut high = IndexMap::new();
+		high.insert(3000, 6000);
+		low.append(&mut high);
+		check_bindings(&low, &[&[2000][..], &keys[..], &[3000][..]].concat());
 	}
-}
 
-impl<'a, K, V> std::iter::FusedIterator for IntoValues<K, V> {}
-impl<'a, K, V> std::iter::ExactSizeIterator for IntoValues<K, V> {}
+	#[test]
+	fn remove_range() {
+		let (keys, mut map) = shuffled(300, 7);
+
+		let drained: Vec<_> = map.drain_range(10..20).collect();
+		assert_eq!(drained, (10..20).map(|k| (k, k * 2)).collect::<Vec<_>>());
+		assert_eq!(map.remove_range(..=5), 6);
+		assert_eq!(map.remove_range(100..), 200);
+		assert_eq!(
+			map.remove_range((Bound::Included(40), Bound::Excluded(30))),
+			0
+		);
+		assert_eq!(map.drain_range(1000..).next(), None);
+
+		let expected: Vec<_> = keys
+			.iter()
+			.copied()
+			.filter(|&k| (6..10).contains(&k) || (20..100).contains(&k))
+			.collect();
+		check_bindings(&map, &expected);
+		assert_eq!(map.count_range(..50), 34);
+	}
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+	#[test]
+	fn drain_range_large() {
+		let (mut keys, mut map) = shuffled(1000, 379);
+		for (start, end) in [(0, 3), (990, 1000), (100, 600), (40, 41), (700, 980)] {
+			let drained: Vec<_> = map.drain_range(start..end).collect();
+			let expected: Vec<_> = (start..end)
+				.filter(|k| keys.contains(k))
+				.map(|k| (k, k * 2))
+				.collect();
+			assert_eq!(drained, expected);
+			keys.retain(|k| !(start..end).contains(k));
+			check_bindings(&map, &keys);
+		}
+
+		map.insert(150, 300);
+		assert_eq!(map.remove_range(..), keys.len() + 1);
+		assert!(map.is_empty());
+	}
 
 	#[test]
-	fn it_works() {
-		let mut map = IndexMap::new();
-		assert_eq!(map.is_empty(), true);
-		map.insert(1, ());
-		map.insert(1, ());
-		assert_eq!(map.len(), 1);
-		assert!(map.get(&1).is_some());
-		assert_eq!(map.is_empty(), false);
+	fn remove_range_edge_cases() {
+		let mut empty: IndexMap<usize, usize> = IndexMap::new();
+		assert_eq!(empty.remove_range(..), 0);
+		assert_eq!(empty.drain_range(5..).next(), None);
+
+		let (keys, mut map) = shuffled(100, 13);
+		let excluded = (Bound::Excluded(50), Bound::Excluded(50));
+		assert_eq!(map.remove_range(excluded), 0);
+		assert_eq!(map.remove_range(50..50), 0);
+		assert_eq!(map.drain_range(200..300).len(), 0);
+		check_bindings(&map, &keys);
+
+		// Single bindings, at both ends and in the middle.
+		for key in [0, 99, 50] {
+			let drained: Vec<_> = map.drain_range(key..=key).collect();
+			assert_eq!(drained, [(key, key * 2)]);
+		}
+		let expected: Vec<_> = keys
+			.iter()
+			.copied()
+			.filter(|k| ![0, 99, 50].contains(k))
+			.collect();
+		check_bindings(&map, &expected);
+
+		// Every binding but the first and last keys.
+		assert_eq!(map.remove_range(2..98), 95);
+		let expected: Vec<_> = keys
+			.iter()
+			.copied()
+			.filter(|&k| k == 1 || k == 98)
+			.collect();
+		check_bindings(&map, &expected);
 	}
 
 	#[test]
-	fn new() {
-		let map = IndexMap::<String, String>::new();
-		println!("{:?}", map);
-		assert_eq!(map.capacity(), 0);
-		assert_eq!(map.len(), 0);
-		assert_eq!(map.is_empty(), true);
+	fn remove_many() {
+		let mut map: IndexMap<_, _> = (0..100).map(|k| (k, k * 2)).collect();
+		let removed = map.shift_remove_many(&[50, 3, 120, 50, 97]);
+		assert_eq!(removed, [(3, 3, 6), (50, 50, 100), (97, 97, 194)]);
+		let expected: Vec<_> = (0..100).filter(|k| ![3, 50, 97].contains(k)).collect();
+		assert!(map.keys().eq(expected.iter()));
+		for (i, key) in expected.iter().enumerate() {
+			assert_eq!(map.get_full(key).map(|(i, _, _)| i), Some(i));
+		}
+
+		// `10` is listed twice: the second occurrence must not remove
+		// the binding that took its place.
+		let keys = [10, 95, 10, 0, 1000];
+		let mut sequential = map.clone();
+		let expected: Vec<_> = keys
+			.iter()
+			.filter_map(|key| sequential.swap_remove_full(key))
+			.collect();
+		let removed = map.swap_remove_many(keys.iter());
+		assert_eq!(removed, expected);
+		assert_eq!(removed, [(9, 10, 20), (93, 95, 190), (0, 0, 0)]);
+		assert!(map.iter().eq(sequential.iter()));
+		assert_eq!(map.len(), 94);
+		assert_eq!(map.get_full(&96), Some((0, &96, &192)));
+		assert_eq!(map.get_full(&99), Some((9, &99, &198)));
+		assert_eq!(map.get_full(&98), Some((93, &98, &196)));
+		assert_eq!(map.get(&95), None);
+		let mut sorted: Vec<_> = map.keys().copied().collect();
+		sorted.sort();
+		assert!(map.keys_sorted().eq(sorted.iter()));
+		assert!(map.swap_remove_many(&[1000]).is_empty());
+		validate(&map);
+
+		// Removing most of the bindings rebuilds the B-Tree.
+		let (_, mut map) = shuffled(100, 37);
+		let keys: Vec<_> = (0..110).rev().filter(|k| k % 4 != 0).collect();
+		let mut sequential = map.clone();
+		let expected: Vec<_> = keys
+			.iter()
+			.filter_map(|key| sequential.swap_remove_full(key))
+			.collect();
+		assert_eq!(map.swap_remove_many(&keys), expected);
+		validate(&map);
+		assert!(map.iter().eq(sequential.iter()));
 	}
 
 	#[test]
-	fn insert() {
-		let insert = [0, 4, 2, 12, 8, 7, 11, 5];
-		let not_present = [1, 3, 6, 9, 10];
-		let mut map = IndexMap::with_capacity(insert.len());
+	fn map_values() {
+		let keys: Vec<_> = (0..100).map(|k| k * 31 % 100).collect();
+		let map: IndexMap<_, _> = keys.iter().map(|&k| (k, k as u32)).collect();
+
+		let map = map.map_values(|k, v| format!("{}:{}", k, v));
+		assert!(map.keys().eq(keys.iter()));
+		for (i, key) in keys.iter().enumerate() {
+			assert_eq!(
+				map.get_full(key),
+				Some((i, key, &format!("{}:{}", key, key)))
+			);
+			assert_eq!(map.rank_of(key), Some(*key));
+		}
 
-		for (i, elt) in insert.into_iter().enumerate() {
-			assert_eq!(map.len(), i);
-			map.insert(elt, elt);
-			assert_eq!(map.len(), i + 1);
-			assert_eq!(map.get(&elt), Some(&elt));
-			assert_eq!(map[&elt], elt);
+		let parsed = map
+			.clone()
+			.try_map_values(|_, v| v[..v.find(':').unwrap()].parse::<u8>());
+		let parsed = parsed.unwrap();
+		assert!(parsed
+			.values()
+			.eq(keys.iter().map(|&k| k as u8).collect::<Vec<_>>().iter()));
+		assert!(map.try_map_values(|_, v| v.parse::<u8>()).is_err());
+	}
+
+	#[test]
+	fn map_values_keeps_layout() {
+		let mut map: IndexMap<_, _> = (0..200).map(|k| (k * 7 % 200, k)).collect();
+		for k in (0..200).step_by(3) {
+			map.swap_remove(&k);
 		}
-		println!("{:?}", map);
 
-		for &elt in &not_present {
-			assert!(map.get(&elt).is_none());
+		fn node_items(nodes: &Slots<Node>) -> Vec<Option<Index>> {
+			nodes
+				.iter()
+				.flat_map(|node| (0..node.item_count()).map(|offset| node.item(offset)))
+				.collect()
 		}
+
+		let vec = map.inner.vec.clone();
+		let items = node_items(&map.nodes);
+		let mut original = map.clone();
+		original.insert(1000, 0);
+
+		let mut map = map.map_values(|_, v| v as u64 * 2);
+		validate(&map);
+		assert_eq!(map.inner.vec, vec);
+		assert_eq!(node_items(&map.nodes), items);
+
+		// The free slots are reused in the same order.
+		map.insert(1000, 0);
+		assert_eq!(map.inner.vec.last(), original.inner.vec.last());
 	}
 
 	#[test]
-	fn insert_full() {
-		let insert = vec![9, 2, 7, 1, 4, 6, 13];
-		let present = vec![1, 6, 2];
-		let mut map = IndexMap::with_capacity(insert.len());
+	fn map_keys() {
+		let (keys, map) = shuffled(100, 17);
+		let mapped = map.clone().map_keys(|k| k + 1000).unwrap();
+		validate(&mapped);
+		assert!(mapped.keys().copied().eq(keys.iter().map(|k| k + 1000)));
+		assert_eq!(mapped.get_full(&1017), Some((1, &1017, &34)));
+		assert_eq!(mapped.rank_of(&1050), Some(50));
+
+		let inverted = map.clone().invert().unwrap();
+		validate(&inverted);
+		assert!(inverted.keys().copied().eq(keys.iter().map(|k| k * 2)));
+		assert_eq!(inverted.get_full(&34), Some((1, &34, &17)));
+
+		let collision = map.map_keys(|k| k % 40).unwrap_err();
+		assert_eq!(collision.indexes().len(), 60);
+		assert_eq!(collision.indexes()[0], (0, 20));
+	}
 
-		for (i, elt) in insert.into_iter().enumerate() {
-			assert_eq!(map.len(), i);
-			let (index, existing) = map.insert_full(elt, elt);
-			assert_eq!(existing, None);
-			assert_eq!(Some(index), map.get_full(&elt).map(|x| x.0));
-			assert_eq!(map.len(), i + 1);
+	#[test]
+	fn map_keys_keeps_capacity() {
+		// Clones do not keep the capacity, hence the map is built anew for each check.
+		let build = || {
+			let mut map: IndexMap<usize, usize> = IndexMap::with_capacity(200);
+			map.extend((0..100).map(|k| (k, k)));
+			for k in (0..100).step_by(3) {
+				map.swap_remove(&k);
+			}
+			map
+		};
+
+		let map = build();
+		let capacity = map.capacity();
+		let vec = map.inner.vec.clone();
+		let mut original = build();
+		original.insert(1000, 0);
+
+		let mut mapped = map.map_keys(|k| k + 1).unwrap();
+		validate(&mapped);
+		assert_eq!(mapped.capacity(), capacity);
+		assert_eq!(mapped.inner.vec, vec);
+
+		// The free slots are reused in the same order.
+		mapped.insert(1001, 0);
+		assert_eq!(mapped.inner.vec.last(), original.inner.vec.last());
+
+		let inverted = build().invert().unwrap();
+		validate(&inverted);
+		assert_eq!(inverted.capacity(), capacity);
+
+		let keys: Vec<_> = build().keys().copied().collect();
+		let collision = build().map_keys(|k| k / 2).unwrap_err();
+		assert!(!collision.indexes().is_empty());
+		for &(a, b) in collision.indexes() {
+			assert_eq!(keys[a] / 2, keys[b] / 2)
 		}
+	}
 
-		let len = map.len();
-		for elt in present {
-			let (index, existing) = map.insert_full(elt, elt);
-			assert_eq!(existing, Some(elt));
-			assert_eq!(Some(index), map.get_full(&elt).map(|x| x.0));
-			assert_eq!(map.len(), len);
+	#[test]
+	fn map_keys_edge_cases() {
+		let empty: IndexMap<usize, usize> = IndexMap::new();
+		assert!(empty.clone().map_keys(|k| k + 1).unwrap().is_empty());
+		assert!(empty.invert().unwrap().is_empty());
+
+		let single: IndexMap<_, _> = [(1, 'a')].into_iter().collect();
+		assert_eq!(single.invert().unwrap().get_full(&'a'), Some((0, &'a', &1)));
+
+		// Several bindings colliding on the same key.
+		let map: IndexMap<_, _> = [('a', 1), ('b', 2), ('c', 1), ('d', 1), ('e', 2)]
+			.into_iter()
+			.collect();
+		let collision = map.invert().unwrap_err();
+		assert_eq!(collision.indexes(), [(0, 2), (0, 3), (1, 4)]);
+		assert_eq!(
+			collision.to_string(),
+			"bindings 0 and 2 have the same key (3 collisions)"
+		);
+
+		// Every binding colliding.
+		let (_, map) = shuffled(50, 7);
+		let collision = map.map_keys(|_| 0).unwrap_err();
+		let expected: Vec<_> = (1..50).map(|i| (0, i)).collect();
+		assert_eq!(collision.indexes(), expected);
+	}
+
+	#[test]
+	fn parts() {
+		let map: IndexMap<_, _> = (0..100).map(|k| (k * 23 % 100, k)).collect();
+		let (keys, values) = map.clone().into_parts();
+		assert!(keys.iter().eq(map.keys()));
+		assert!(values.iter().eq(map.values()));
+		assert_eq!(keys.get_index_of(&23), Some(1));
+		assert_eq!(values.iter().sum::<i32>(), 4950);
+
+		let values: Vec<_> = values.into_iter().map(|v| v * 2).collect();
+		let doubled = IndexMap::from_parts(keys, values);
+		validate(&doubled);
+		assert_eq!(doubled.inner.vec, map.inner.vec);
+		assert!(doubled.keys().eq(map.keys()));
+		assert_eq!(doubled.get_full(&23), Some((1, &23, &2)));
+		assert_eq!(doubled.rank_of(&50), Some(50));
+	}
+
+	#[test]
+	fn try_insert() {
+		let mut map = IndexMap::new();
+		assert_eq!(map.try_insert("a", 1).ok().copied(), Some(1));
+		*map.try_insert("b", 2).unwrap() += 10;
+		let error = map.try_insert("a", 3).unwrap_err();
+		assert_eq!(error.value, 3);
+		assert_eq!(error.entry.index(), 0);
+		assert_eq!(error.entry.get(), &1);
+		assert_eq!(map.get("b"), Some(&12));
+		assert_eq!(map.len(), 2);
+
+		let map = IndexMap::try_from_iter([(3, 'a'), (1, 'b'), (2, 'c')]).unwrap();
+		assert!(map.keys().eq([3, 1, 2].iter()));
+		assert_eq!(
+			IndexMap::try_from_iter([(3, 'a'), (1, 'b'), (2, 'c'), (1, 'd')]),
+			Err(DuplicateKey {
+				first_index: 1,
+				second_index: 3,
+				key: 1
+			})
+		);
+	}
+
+	#[test]
+	fn accessors() {
+		let mut map: IndexMap<_, _> = [(5, 'a'), (3, 'b'), (8, 'c')].into_iter().collect();
+		assert_eq!(map.get_index_of(&3), Some(1));
+		assert_eq!(map.get_index_of(&4), None);
+		assert_eq!(map.get_key_value(&8), Some((&8, &'c')));
+		assert!(map.contains_key(&5));
+		assert!(!map.contains_key(&6));
+
+		if let Some((i, _, value)) = map.get_full_mut(&3) {
+			assert_eq!(i, 1);
+			*value = 'x';
 		}
+		assert_eq!(map.get(&3), Some(&'x'));
+
+		assert_eq!(map.first(), Some((&5, &'a')));
+		assert_eq!(map.last(), Some((&8, &'c')));
+		*map.first_mut().unwrap().1 = 'y';
+		*map.last_mut().unwrap().1 = 'z';
+		assert!(map
+			.iter_full()
+			.eq([(0, &5, &'y'), (1, &3, &'x'), (2, &8, &'z')]));
+		assert_eq!(map.iter_full().next_back(), Some((2, &8, &'z')));
+
+		assert_eq!(map.pop(), Some((8, 'z')));
+		assert_eq!(map.pop(), Some((3, 'x')));
+		assert_eq!(map.get(&3), None);
+		assert_eq!(map.pop(), Some((5, 'y')));
+		assert_eq!(map.pop(), None);
+		assert_eq!(map.first(), None);
+		assert_eq!(map.last_mut(), None);
 	}
 
-	// #[test]
-	// fn insert_2() {
-	//     let mut map = IndexMap::with_capacity(16);
-
-	//     let mut keys = vec![];
-	//     keys.extend(0..16);
-	//     keys.extend(128..267);
-
-	//     for &i in &keys {
-	//         let old_map = map.clone();
-	//         map.insert(i, ());
-	//         for key in old_map.keys() {
-	//             if map.get(key).is_none() {
-	//                 println!("old_map: {:?}", old_map);
-	//                 println!("map: {:?}", map);
-	//                 panic!("did not find {} in map", key);
-	//             }
-	//         }
-	//     }
+	#[test]
+	fn partial_eq_and_eq() {
+		let mut map_a = IndexMap::new();
+		map_a.insert(1, "1");
+		map_a.insert(2, "2");
+		let mut map_b = map_a.clone();
+		assert_eq!(map_a, map_b);
+		map_b.swap_remove(&1);
+		assert_ne!(map_a, map_b);
+
+		let map_c: IndexMap<_, String> = map_b.into_iter().map(|(k, v)| (k, v.into())).collect();
+		assert_ne!(map_a, map_c);
+		assert_ne!(map_c, map_a);
+	}
 
-	//     for &i in &keys {
-	//         assert!(map.get(&i).is_some(), "did not find {}", i);
-	//     }
-	// }
+	fn hash_of<T: Hash>(t: &T) -> u64 {
+		let mut hasher = std::collections::hash_map::DefaultHasher::new();
+		t.hash(&mut hasher);
+		hasher.finish()
+	}
 
-	// #[test]
-	// fn insert_order() {
-	//     let insert = [0, 4, 2, 12, 8, 7, 11, 5, 3, 17, 19, 22, 23];
-	//     let mut map = IndexMap::new();
+	#[test]
+	fn eq_and_hash_ignore_order() {
+		let mut map_a = IndexMap::new();
+		let mut map_b = IndexMap::new();
+		for i in 0..100 {
+			map_a.insert(i, i * 2);
+			map_b.insert(99 - i, (99 - i) * 2);
+		}
 
-	//     for &elt in &insert {
-	//         map.insert(elt, ());
-	//     }
+		assert_eq!(map_a, map_b);
+		assert_eq!(hash_of(&map_a), hash_of(&map_b));
 
-	//     assert_eq!(map.keys().count(), map.len());
-	//     assert_eq!(map.keys().count(), insert.len());
-	//     for (a, b) in insert.iter().zip(map.keys()) {
-	//         assert_eq!(a, b);
-	//     }
-	//     for (i, k) in (0..insert.len()).zip(map.keys()) {
-	//         assert_eq!(map.get_index(i).unwrap().0, k);
-	//     }
-	// }
+		let mut set = std::collections::HashSet::new();
+		set.insert(map_a);
+		assert!(set.contains(&map_b));
 
-	// #[test]
-	// fn grow() {
-	//     let insert = [0, 4, 2, 12, 8, 7, 11];
-	//     let not_present = [1, 3, 6, 9, 10];
-	//     let mut map = IndexMap::with_capacity(insert.len());
+		map_b.insert(42, 0);
+		assert_ne!(set.iter().next().unwrap(), &map_b);
+		map_b.swap_remove(&42);
+		assert_ne!(set.iter().next().unwrap(), &map_b);
+	}
 
-	//     for (i, &elt) in enumerate(&insert) {
-	//         assert_eq!(map.len(), i);
-	//         map.insert(elt, elt);
-	//         assert_eq!(map.len(), i + 1);
-	//         assert_eq!(map.get(&elt), Some(&elt));
-	//         assert_eq!(map[&elt], elt);
-	//     }
+	#[test]
+	fn ord_ignores_order() {
+		let mut map_a = IndexMap::new();
+		map_a.insert(2, 'b');
+		map_a.insert(1, 'a');
+		let mut map_b = IndexMap::new();
+		map_b.insert(1, 'a');
+		map_b.insert(3, 'a');
+		let mut map_c = IndexMap::new();
+		map_c.insert(1, 'a');
+
+		assert!(map_a < map_b);
+		assert!(map_c < map_a);
+		assert_eq!(map_a.partial_cmp(&map_a), Some(Ordering::Equal));
+
+		let mut map_d = IndexMap::new();
+		map_d.insert(1, 'a');
+		map_d.insert(2, 'b');
+		assert_eq!(map_a.cmp(&map_d), Ordering::Equal);
+
+		let set: std::collections::BTreeSet<_> = [map_b, map_a, map_c].into_iter().collect();
+		assert_eq!(set.len(), 3);
+		assert!(set.contains(&map_d));
+	}
 
-	//     println!("{:?}", map);
-	//     for &elt in &insert {
-	//         map.insert(elt * 10, elt);
-	//     }
-	//     for &elt in &insert {
-	//         map.insert(elt * 100, elt);
-	//     }
-	//     for (i, &elt) in insert.iter().cycle().enumerate().take(100) {
-	//         map.insert(elt * 100 + i as i32, elt);
-	//     }
-	//     println!("{:?}", map);
-	//     for &elt in &not_present {
-	//         assert!(map.get(&elt).is_none());
-	//     }
-	// }
+	#[test]
+	fn clone() {
+		let mut map = IndexMap::new();
+		for i in 0..100 {
+			map.insert((i * 37) % 100, i);
+		}
+		map.swap_remove(&12);
 
-	// #[test]
-	// fn reserve() {
-	//     let mut map = IndexMap::<usize, usize>::new();
-	//     assert_eq!(map.capacity(), 0);
-	//     map.reserve(100);
-	//     let capacity = map.capacity();
-	//     assert!(capacity >= 100);
-	//     for i in 0..capacity {
-	//         assert_eq!(map.len(), i);
-	//         map.insert(i, i * i);
-	//         assert_eq!(map.len(), i + 1);
-	//         assert_eq!(map.capacity(), capacity);
-	//         assert_eq!(map.get(&i), Some(&(i * i)));
-	//     }
-	//     map.insert(capacity, std::usize::MAX);
-	//     assert_eq!(map.len(), capacity + 1);
-	//     assert!(map.capacity() > capacity);
-	//     assert_eq!(map.get(&capacity), Some(&std::usize::MAX));
-	// }
+		let clone = map.clone();
+		assert_eq!(clone, map);
+		for i in 0..map.len() {
+			assert_eq!(clone.get_index(i), map.get_index(i));
+		}
 
-	// #[test]
-	// fn shrink_to_fit() {
-	//     let mut map = IndexMap::<usize, usize>::new();
-	//     assert_eq!(map.capacity(), 0);
-	//     for i in 0..100 {
-	//         assert_eq!(map.len(), i);
-	//         map.insert(i, i * i);
-	//         assert_eq!(map.len(), i + 1);
-	//         assert!(map.capacity() >= i + 1);
-	//         assert_eq!(map.get(&i), Some(&(i * i)));
-	//         map.shrink_to_fit();
-	//         assert_eq!(map.len(), i + 1);
-	//         assert_eq!(map.capacity(), i + 1);
-	//         assert_eq!(map.get(&i), Some(&(i * i)));
-	//     }
-	// }
+		let mut other = IndexMap::new();
+		other.insert(1000, 0);
+		other.clone_from(&clone);
+		assert_eq!(other, map);
+		assert_eq!(other.get(&1000), None);
+		for i in 0..map.len() {
+			assert_eq!(other.get_index(i), map.get_index(i));
+			let (key, value) = map.get_index(i).unwrap();
+			assert_eq!(other.get_full(key), Some((i, key, value)));
+		}
+	}
 
-	// #[test]
-	// fn remove() {
-	//     let insert = [0, 4, 2, 12, 8, 7, 11, 5, 3, 17, 19, 22, 23];
-	//     let mut map = IndexMap::new();
+	#[test]
+	fn extend() {
+		let mut map = IndexMap::new();
+		map.extend(vec![(&1, &2), (&3, &4)]);
+		map.extend(vec![(5, 6)]);
+		assert_eq!(
+			map.into_iter().collect::<Vec<_>>(),
+			vec![(1, 2), (3, 4), (5, 6)]
+		);
+
+		// The whole size hint is reserved when the map is not empty.
+		let mut map: IndexMap<_, _> = [(0, 0)].into_iter().collect();
+		map.extend((1..100).map(|k| (k, k)));
+		assert_eq!(map.len(), 100);
+		assert!(map.capacity() >= 100);
+		validate(&map);
+	}
 
-	//     for &elt in &insert {
-	//         map.insert(elt, elt);
-	//     }
+	#[test]
+	fn from_iter_duplicates() {
+		let map: IndexMap<_, _> = vec![(1, 'a'), (2, 'b'), (1, 'c'), (3, 'd'), (2, 'e'), (1, 'f')]
+			.into_iter()
+			.collect();
+		assert_eq!(map.len(), 3);
+		assert!(map.iter().eq([(&1, &'f'), (&2, &'e'), (&3, &'d')]));
+	}
 
-	//     assert_eq!(map.keys().count(), map.len());
-	//     assert_eq!(map.keys().count(), insert.len());
-	//     for (a, b) in insert.iter().zip(map.keys()) {
-	//         assert_eq!(a, b);
-	//     }
+	#[test]
+	fn from_iter_large() {
+		let mut map: IndexMap<_, _> = (0..1000).map(|i| ((i * 7919) % 1000, i)).collect();
+		assert_eq!(map.len(), 1000);
+		for i in 0..1000 {
+			let key = (i * 7919) % 1000;
+			assert_eq!(map.get_full(&key), Some((i, &key, &i)));
+		}
 
-	//     let remove_fail = [99, 77];
-	//     let remove = [4, 12, 8, 7];
+		for i in 1000..1500 {
+			map.insert(i, i);
+		}
+		for i in (0..1500).step_by(3) {
+			assert!(map.swap_remove(&i).is_some());
+		}
+		for i in 0..1500 {
+			assert_eq!(map.get(&i).is_some(), i % 3 != 0);
+		}
+	}
 
-	//     for &key in &remove_fail {
-	//         assert!(map.swap_remove_full(&key).is_none());
-	//     }
-	//     println!("{:?}", map);
-	//     for &key in &remove {
-	//         //println!("{:?}", map);
-	//         let index = map.get_full(&key).unwrap().0;
-	//         assert_eq!(map.swap_remove_full(&key), Some((index, key, key)));
-	//     }
-	//     println!("{:?}", map);
+	#[test]
+	fn entry() {
+		let mut map = IndexMap::new();
 
-	//     for key in &insert {
-	//         assert_eq!(map.get(key).is_some(), !remove.contains(key));
-	//     }
-	//     assert_eq!(map.len(), insert.len() - remove.len());
-	//     assert_eq!(map.keys().count(), insert.len() - remove.len());
-	// }
+		map.insert(1, "1");
+		map.insert(2, "2");
+		{
+			let e = map.entry(3);
+			assert_eq!(e.index(), 2);
+			let e = e.or_insert("3");
+			assert_eq!(e, &"3");
+		}
 
-	// #[test]
-	// fn remove_to_empty() {
-	//     let mut map = indexmap! { 0 => 0, 4 => 4, 5 => 5 };
-	//     map.swap_remove(&5).unwrap();
-	//     map.swap_remove(&4).unwrap();
-	//     map.swap_remove(&0).unwrap();
-	//     assert!(map.is_empty());
-	// }
+		let e = map.entry(2);
+		assert_eq!(e.index(), 1);
+		assert_eq!(e.key(), &2);
+		match e {
+			Entry::Occupied(ref e) => assert_eq!(e.get(), &"2"),
+			Entry::Vacant(_) => panic!(),
+		}
+		assert_eq!(e.or_insert("4"), &"2");
+	}
 
-	// #[test]
-	// fn swap_remove_index() {
-	//     let insert = [0, 4, 2, 12, 8, 7, 11, 5, 3, 17, 19, 22, 23];
-	//     let mut map = IndexMap::new();
+	#[test]
+	fn entry_and_modify() {
+		let mut map = IndexMap::new();
 
-	//     for &elt in &insert {
-	//         map.insert(elt, elt * 2);
-	//     }
+		map.insert(1, "1");
+		map.entry(1).and_modify(|x| *x = "2");
+		assert_eq!(Some(&"2"), map.get(&1));
 
-	//     let mut vector = insert.to_vec();
-	//     let remove_sequence = &[3, 3, 10, 4, 5, 4, 3, 0, 1];
+		map.entry(2).and_modify(|x| *x = "doesn't exist");
+		assert_eq!(None, map.get(&2));
+	}
 
-	//     // check that the same swap remove sequence on vec and map
-	//     // have the same result.
-	//     for &rm in remove_sequence {
-	//         let out_vec = vector.swap_remove(rm);
-	//         let (out_map, _) = map.swap_remove_index(rm).unwrap();
-	//         assert_eq!(out_vec, out_map);
-	//     }
-	//     assert_eq!(vector.len(), map.len());
-	//     for (a, b) in vector.iter().zip(map.keys()) {
-	//         assert_eq!(a, b);
-	//     }
-	// }
+	#[test]
+	fn entry_or_default() {
+		let mut map = IndexMap::new();
 
-	// #[test]
-	// fn partial_eq_and_eq() {
-	//     let mut map_a = IndexMap::new();
-	//     map_a.insert(1, "1");
-	//     map_a.insert(2, "2");
-	//     let mut map_b = map_a.clone();
-	//     assert_eq!(map_a, map_b);
-	//     map_b.swap_remove(&1);
-	//     assert_ne!(map_a, map_b);
-
-	//     let map_c: IndexMap<_, String> = map_b.into_iter().map(|(k, v)| (k, v.into())).collect();
-	//     assert_ne!(map_a, map_c);
-	//     assert_ne!(map_c, map_a);
-	// }
+		#[derive(Debug, Default, PartialEq)]
+		enum TestEnum {
+			#[default]
+			DefaultValue,
+			NonDefaultValue,
+		}
 
-	// #[test]
-	// fn extend() {
-	//     let mut map = IndexMap::new();
-	//     map.extend(vec![(&1, &2), (&3, &4)]);
-	//     map.extend(vec![(5, 6)]);
-	//     assert_eq!(
-	//         map.into_iter().collect::<Vec<_>>(),
-	//         vec![(1, 2), (3, 4), (5, 6)]
-	//     );
-	// }
+		map.insert(1, TestEnum::NonDefaultValue);
+		assert_eq!(&mut TestEnum::NonDefaultValue, map.entry(1).or_default());
 
-	// #[test]
-	// fn entry() {
-	//     let mut map = IndexMap::new();
-
-	//     map.insert(1, "1");
-	//     map.insert(2, "2");
-	//     {
-	//         let e = map.entry(3);
-	//         assert_eq!(e.index(), 2);
-	//         let e = e.or_insert("3");
-	//         assert_eq!(e, &"3");
-	//     }
+		assert_eq!(&mut TestEnum::DefaultValue, map.entry(2).or_default());
+	}
 
-	//     let e = map.entry(2);
-	//     assert_eq!(e.index(), 1);
-	//     assert_eq!(e.key(), &2);
-	//     match e {
-	//         Entry::Occupied(ref e) => assert_eq!(e.get(), &"2"),
-	//         Entry::Vacant(_) => panic!(),
-	//     }
-	//     assert_eq!(e.or_insert("4"), &"2");
-	// }
+	#[test]
+	fn occupied_entry_key() {
+		// These keys match hash and equality, but their addresses are distinct.
+		let (k1, k2) = (&mut 1, &mut 1);
+		let k1_ptr = k1 as *const i32;
+		let k2_ptr = k2 as *const i32;
+		assert_ne!(k1_ptr, k2_ptr);
 
-	// #[test]
-	// fn entry_and_modify() {
-	//     let mut map = IndexMap::new();
+		let mut map = IndexMap::new();
+		map.insert(k1, "value");
+		match map.entry(k2) {
+			Entry::Occupied(ref e) => {
+				// `OccupiedEntry::key` should reference the key in the map,
+				// not the key that was used to find the entry.
+				let ptr = *e.key() as *const i32;
+				assert_eq!(ptr, k1_ptr);
+				assert_ne!(ptr, k2_ptr);
+			}
+			Entry::Vacant(_) => panic!(),
+		}
+	}
 
-	//     map.insert(1, "1");
-	//     map.entry(1).and_modify(|x| *x = "2");
-	//     assert_eq!(Some(&"2"), map.get(&1));
+	#[test]
+	fn entry_counting() {
+		let mut map = IndexMap::new();
+		for i in 0..200 {
+			*map.entry(i % 37).or_insert(0) += 1;
+		}
 
-	//     map.entry(2).and_modify(|x| *x = "doesn't exist");
-	//     assert_eq!(None, map.get(&2));
-	// }
+		assert_eq!(map.len(), 37);
+		for i in 0..37 {
+			let count = if i < 200 % 37 { 6 } else { 5 };
+			assert_eq!(map.get_index(i), Some((&i, &count)));
+		}
 
-	// #[test]
-	// fn entry_or_default() {
-	//     let mut map = IndexMap::new();
+		match map.entry(3) {
+			Entry::Occupied(e) => assert_eq!(e.shift_remove_entry(), (3, 6)),
+			Entry::Vacant(_) => panic!(),
+		}
+		assert_eq!(map.get(&3), None);
+		assert_eq!(map.get_index(3), Some((&4, &6)));
+	}
 
-	//     #[derive(Debug, PartialEq)]
-	//     enum TestEnum {
-	//         DefaultValue,
-	//         NonDefaultValue,
-	//     }
+	#[test]
+	fn entry_splits() {
+		let keys: Vec<_> = (0..1000).map(|k| k * 379 % 1000).collect();
+		let mut map = IndexMap::new();
+		for &k in &keys {
+			assert_eq!(map.entry(k).or_insert(k * 2), &(k * 2));
+		}
 
-	//     impl Default for TestEnum {
-	//         fn default() -> Self {
-	//             TestEnum::DefaultValue
-	//         }
-	//     }
+		validate(&map);
+		assert!(map.keys().eq(keys.iter()));
+		assert!(map.keys_sorted().copied().eq(0..1000));
+		for (i, k) in keys.iter().enumerate() {
+			assert_eq!(map.rank_of(k), Some(*k));
+			assert_eq!(map.get_full(k), Some((i, k, &(k * 2))));
+		}
 
-	//     map.insert(1, TestEnum::NonDefaultValue);
-	//     assert_eq!(&mut TestEnum::NonDefaultValue, map.entry(1).or_default());
+		for k in (0..1000).step_by(2) {
+			assert_eq!(map.swap_remove(&k), Some(k * 2));
+		}
+		for k in (0..1000).step_by(4) {
+			assert!(matches!(map.entry(k), Entry::Vacant(_)));
+			map.entry(k).or_insert(k);
+		}
+		validate(&map);
+		assert_eq!(map.len(), 750);
+		for (rank, k) in map.keys_sorted().enumerate() {
+			assert_eq!(map.rank_of(k), Some(rank));
+		}
+	}
 
-	//     assert_eq!(&mut TestEnum::DefaultValue, map.entry(2).or_default());
-	// }
+	#[test]
+	fn entry_random() {
+		// Linear congruential generator, for reproducible pseudo-random keys.
+		let mut state: u64 = 42;
+		let mut random_key = move || {
+			state = state
+				.wrapping_mul(6364136223846793005)
+				.wrapping_add(1442695040888963407);
+			(state >> 33) as usize % 2000
+		};
 
-	// #[test]
-	// fn occupied_entry_key() {
-	//     // These keys match hash and equality, but their addresses are distinct.
-	//     let (k1, k2) = (&mut 1, &mut 1);
-	//     let k1_ptr = k1 as *const i32;
-	//     let k2_ptr = k2 as *const i32;
-	//     assert_ne!(k1_ptr, k2_ptr);
-
-	//     let mut map = IndexMap::new();
-	//     map.insert(k1, "value");
-	//     match map.entry(k2) {
-	//         Entry::Occupied(ref e) => {
-	//             // `OccupiedEntry::key` should reference the key in the map,
-	//             // not the key that was used to find the entry.
-	//             let ptr = *e.key() as *const i32;
-	//             assert_eq!(ptr, k1_ptr);
-	//             assert_ne!(ptr, k2_ptr);
-	//         }
-	//         Entry::Vacant(_) => panic!(),
-	//     }
-	// }
+		let mut map = IndexMap::new();
+		for i in 0..6000 {
+			let key = random_key();
+			if i % 6 == 2 {
+				map.swap_remove(&key);
+			} else if i % 6 == 5 {
+				if let Entry::Occupied(entry) = map.entry(key) {
+					entry.shift_remove();
+				}
+			} else {
+				*map.entry(key).or_insert(0) += 1;
+			}
 
-	// #[test]
-	// fn keys() {
-	//     let vec = vec![(1, 'a'), (2, 'b'), (3, 'c')];
-	//     let map: IndexMap<_, _> = vec.into_iter().collect();
-	//     let keys: Vec<_> = map.keys().copied().collect();
-	//     assert_eq!(keys.len(), 3);
-	//     assert!(keys.contains(&1));
-	//     assert!(keys.contains(&2));
-	//     assert!(keys.contains(&3));
-	// }
+			if i % 50 == 0 {
+				validate(&map)
+			}
+		}
 
-	// #[test]
-	// fn values() {
-	//     let vec = vec![(1, 'a'), (2, 'b'), (3, 'c')];
-	//     let map: IndexMap<_, _> = vec.into_iter().collect();
-	//     let values: Vec<_> = map.values().copied().collect();
-	//     assert_eq!(values.len(), 3);
-	//     assert!(values.contains(&'a'));
-	//     assert!(values.contains(&'b'));
-	//     assert!(values.contains(&'c'));
-	// }
+		validate(&map);
+	}
 
-	// #[test]
-	// fn values_mut() {
-	//     let vec = vec![(1, 1), (2, 2), (3, 3)];
-	//     let mut map: IndexMap<_, _> = vec.into_iter().collect();
-	//     for value in map.values_mut() {
-	//         *value *= 2
-	//     }
-	//     let values: Vec<_> = map.values().copied().collect();
-	//     assert_eq!(values.len(), 3);
-	//     assert!(values.contains(&2));
-	//     assert!(values.contains(&4));
-	//     assert!(values.contains(&6));
-	// }
+	#[test]
+	fn keys() {
+		let vec = vec![(1, 'a'), (2, 'b'), (3, 'c')];
+		let map: IndexMap<_, _> = vec.into_iter().collect();
+		let keys: Vec<_> = map.keys().copied().collect();
+		assert_eq!(keys.len(), 3);
+		assert!(keys.contains(&1));
+		assert!(keys.contains(&2));
+		assert!(keys.contains(&3));
+	}
+
+	#[test]
+	fn values() {
+		let vec = vec![(1, 'a'), (2, 'b'), (3, 'c')];
+		let map: IndexMap<_, _> = vec.into_iter().collect();
+		let values: Vec<_> = map.values().copied().collect();
+		assert_eq!(values.len(), 3);
+		assert!(values.contains(&'a'));
+		assert!(values.contains(&'b'));
+		assert!(values.contains(&'c'));
+	}
+
+	#[test]
+	fn values_mut() {
+		let vec = vec![(1, 1), (2, 2), (3, 3)];
+		let mut map: IndexMap<_, _> = vec.into_iter().collect();
+		for value in map.values_mut() {
+			*value *= 2
+		}
+		let values: Vec<_> = map.values().copied().collect();
+		assert_eq!(values.len(), 3);
+		assert!(values.contains(&2));
+		assert!(values.contains(&4));
+		assert!(values.contains(&6));
+	}
 }
@@ -1,7 +1,908 @@
+use crate::{div_ceil, slots::Slots, Index, M};
+use std::collections::TryReserveError;
+
 pub mod internal;
 pub mod leaf;
 
+#[derive(Clone)]
 pub enum Node {
 	Internal(internal::Metadata),
 	Leaf(leaf::Metadata),
 }
+
+impl Node {
+	/// Returns the parent node id, if any.
+	#[inline]
+	pub fn parent(&self) -> Option<usize> {
+		match self {
+			Self::Internal(node) => node.parent(),
+			Self::Leaf(node) => node.parent(),
+		}
+	}
+
+	#[inline]
+	pub fn set_parent(&mut self, parent: Option<usize>) {
+		match self {
+			Self::Internal(node) => node.set_parent(parent),
+			Self::Leaf(node) => node.set_parent(parent),
+		}
+	}
+
+	/// Returns the number of items stored in the node.
+	#[inline]
+	pub fn item_count(&self) -> usize {
+		match self {
+			Self::Internal(node) => node.item_count(),
+			Self::Leaf(node) => node.item_count(),
+		}
+	}
+
+	/// Returns the number of items in the subtree rooted at this node.
+	#[inline]
+	pub fn subtree_len(&self) -> usize {
+		match self {
+			Self::Internal(node) => node.subtree_len(),
+			Self::Leaf(node) => node.item_count(),
+		}
+	}
+
+	/// Returns the item at the given offset in the node.
+	#[inline]
+	pub fn item(&self, offset: usize) -> Option<Index> {
+		match self {
+			Self::Internal(node) => node.item(offset),
+			Self::Leaf(node) => node.item(offset),
+		}
+	}
+
+	/// Replaces the item at the given offset in the node.
+	#[inline]
+	pub fn set_item(&mut self, offset: usize, item: Index) {
+		match self {
+			Self::Internal(node) => node.set_item(offset, item),
+			Self::Leaf(node) => node.set_item(offset, item),
+		}
+	}
+
+	/// Returns the id of the child at the given position.
+	///
+	/// Always returns `None` for leaves.
+	#[inline]
+	pub fn child_id(&self, position: usize) -> Option<usize> {
+		match self {
+			Self::Internal(node) => node.child_id(position),
+			Self::Leaf(_) => None,
+		}
+	}
+
+	/// Returns the position of the given child in the node.
+	///
+	/// Always returns `None` for leaves.
+	#[inline]
+	pub fn child_position(&self, id: usize) -> Option<usize> {
+		match self {
+			Self::Internal(node) => node.child_position(id),
+			Self::Leaf(_) => None,
+		}
+	}
+
+	/// Returns the maximum number of items the node can hold.
+	///
+	/// See [`MAX_LEAF_ITEMS`] and [`MAX_INTERNAL_ITEMS`].
+	#[inline]
+	fn max_item_count(&self) -> usize {
+		match self {
+			Self::Internal(_) => MAX_INTERNAL_ITEMS,
+			Self::Leaf(_) => MAX_LEAF_ITEMS,
+		}
+	}
+
+	/// Inserts an item at the given offset, with its right child.
+	///
+	/// The child must be `None` for leaves, and `Some` for internal nodes.
+	/// The caller must ensure that the node does not overflow.
+	#[inline]
+	fn insert(&mut self, offset: usize, item: Index, child_id: Option<usize>) {
+		match self {
+			Self::Internal(node) => node.insert(offset, item, child_id.unwrap()),
+			Self::Leaf(node) => node.insert(offset, item),
+		}
+	}
+
+	/// Replaces the child at the given position.
+	///
+	/// ## Panics
+	///
+	/// Panics if the node is a leaf.
+	#[inline]
+	pub fn set_child_id(&mut self, position: usize, id: usize) {
+		match self {
+			Self::Internal(node) => node.set_child_id(position, id),
+			Self::Leaf(_) => panic!("leaves have no children"),
+		}
+	}
+
+	/// Appends an item with its right child at the end of the node.
+	#[inline]
+	fn push_back(&mut self, item: Index, child_id: Option<usize>) {
+		self.insert(self.item_count(), item, child_id)
+	}
+
+	/// Removes the last item with its right child.
+	#[inline]
+	fn pop_back(&mut self) -> (Index, Option<usize>) {
+		match self {
+			Self::Internal(node) => {
+				let (item, child_id) = node.pop_back();
+				(item, Some(child_id))
+			}
+			Self::Leaf(node) => (node.pop_back(), None),
+		}
+	}
+
+	/// Inserts an item with its left child at the front of the node.
+	#[inline]
+	fn push_front(&mut self, child_id: Option<usize>, item: Index) {
+		match self {
+			Self::Internal(node) => node.push_front(child_id.unwrap(), item),
+			Self::Leaf(node) => node.insert(0, item),
+		}
+	}
+
+	/// Removes the first child with the first item.
+	#[inline]
+	fn pop_front(&mut self) -> (Option<usize>, Index) {
+		match self {
+			Self::Internal(node) => {
+				let (child_id, item) = node.pop_front();
+				(Some(child_id), item)
+			}
+			Self::Leaf(node) => (None, node.pop_front()),
+		}
+	}
+
+	/// Appends the given separator item and the content of the given node,
+	/// which must have the same height.
+	#[inline]
+	fn append(&mut self, separator: Index, other: Self) {
+		match (self, other) {
+			(Self::Internal(node), Self::Internal(other)) => node.append(separator, other),
+			(Self::Leaf(node), Self::Leaf(other)) => node.append(separator, other),
+			_ => unreachable!(),
+		}
+	}
+
+	/// Splits the node around the item at the given offset.
+	///
+	/// Returns this item, and a new node holding the items that follow it.
+	#[inline]
+	fn split_off(&mut self, offset: usize) -> (Index, Self) {
+		match self {
+			Self::Internal(node) => {
+				let (item, right) = node.split_off(offset);
+				(item, Self::Internal(right))
+			}
+			Self::Leaf(node) => {
+				let (item, right) = node.split_off(offset);
+				(item, Self::Leaf(right))
+			}
+		}
+	}
+}
+
+/// Address of an item in the B-Tree.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Address {
+	/// Node id.
+	pub id: usize,
+
+	/// Offset of the item in the node.
+	pub offset: usize,
+}
+
+impl Address {
+	#[inline]
+	pub fn new(id: usize, offset: usize) -> Self {
+		Self { id, offset }
+	}
+}
+
+impl From<generic_btree::Address> for Address {
+	/// Converts an address pointing to an item.
+	#[inline]
+	fn from(address: generic_btree::Address) -> Self {
+		Self::new(address.id, address.offset.value().unwrap())
+	}
+}
+
+/// Minimum number of items in a non-root node.
+const MIN_ITEMS: usize = M / 2 - 1;
+
+/// Returns the maximum number of nodes of a B-Tree holding the given number of items.
+///
+/// Every node but the root contains at least [`MIN_ITEMS`] items.
+#[inline]
+pub fn nodes_for(items: usize) -> usize {
+	match items {
+		0 => 0,
+		_ => 1 + div_ceil(items - 1, MIN_ITEMS),
+	}
+}
+
+/// Returns the number of items a B-Tree can always hold with the given number of nodes.
+///
+/// This is the inverse of [`nodes_for`].
+#[inline]
+pub fn items_for(nodes: usize) -> usize {
+	match nodes {
+		0 => 0,
+		_ => (nodes - 1) * MIN_ITEMS + 1,
+	}
+}
+
+/// Makes sure that the given node storage can hold a B-Tree of the given
+/// number of items without reallocating.
+#[inline]
+pub fn reserve(nodes: &mut Slots<Node>, items: usize) {
+	let needed = nodes_for(items);
+	if nodes.capacity() < needed {
+		nodes.reserve_exact(needed - nodes.len())
+	}
+}
+
+/// Fallible version of [`reserve`].
+#[inline]
+pub fn try_reserve(nodes: &mut Slots<Node>, items: usize) -> Result<(), TryReserveError> {
+	let needed = nodes_for(items);
+	if nodes.capacity() < needed {
+		nodes.try_reserve_exact(needed - nodes.len())?
+	}
+
+	Ok(())
+}
+
+/// Returns the maximum number of items in a built tree of the given height.
+///
+/// Built nodes contain at most `M - 1` items.
+fn built_capacity(height: usize) -> usize {
+	let mut capacity = M - 1;
+	for _ in 0..height {
+		capacity = capacity.saturating_mul(M).saturating_add(M - 1)
+	}
+
+	capacity
+}
+
+/// Builds a B-Tree bottom-up from the given items, sorted by key.
+///
+/// Items are evenly distributed among the nodes so that every node but the
+/// root is at least half full.
+/// Returns the id of the root node, if any.
+///
+/// Computes in **O(n)** time, without comparing any item.
+pub fn build(nodes: &mut Slots<Node>, items: &[Index]) -> Option<usize> {
+	if items.is_empty() {
+		return None;
+	}
+
+	let mut height = 0;
+	while built_capacity(height) < items.len() {
+		height += 1
+	}
+
+	Some(build_subtree(nodes, items, height, None))
+}
+
+fn build_subtree(
+	nodes: &mut Slots<Node>,
+	items: &[Index],
+	height: usize,
+	parent: Option<usize>,
+) -> usize {
+	if height == 0 {
+		return nodes.insert(Node::Leaf(leaf::Metadata::new(parent, items)));
+	}
+
+	let id = nodes.insert(Node::Internal(internal::Metadata::new(parent)));
+
+	let child_count = div_ceil(items.len() + 1, built_capacity(height - 1) + 1);
+	let child_items = items.len() + 1 - child_count;
+	let mut start = 0;
+	for c in 0..child_count {
+		let separator = if c > 0 {
+			start += 1;
+			Some(items[start - 1])
+		} else {
+			None
+		};
+
+		let len = child_items / child_count + usize::from(c < child_items % child_count);
+		let child_id = build_subtree(nodes, &items[start..(start + len)], height - 1, Some(id));
+		start += len;
+
+		match &mut nodes[id] {
+			Node::Internal(node) => match separator {
+				Some(separator) => node.push_right(separator, child_id),
+				None => node.set_first_child_id(child_id),
+			},
+			Node::Leaf(_) => unreachable!(),
+		}
+	}
+
+	debug_assert_eq!(start, items.len());
+	if let Node::Internal(node) = &mut nodes[id] {
+		node.set_subtree_len(items.len())
+	}
+
+	id
+}
+
+/// Marks the subtree length of the given node and of its ancestors as stale.
+///
+/// The ancestors of a stale node are stale too,
+/// so the walk stops at the first node already marked.
+/// It also stops at a parent that does not list the node as a child anymore,
+/// which happens while the node is being moved to another parent:
+/// this other parent is marked when the node is attached to it.
+/// Leaves compute their length from their items, and are never marked.
+///
+/// Computes in **O(log n)** time, without allocating.
+pub fn mark_stale(nodes: &mut Slots<Node>, mut id: usize) {
+	loop {
+		if let Node::Internal(node) = &mut nodes[id] {
+			if !node.mark_stale() {
+				break;
+			}
+		}
+
+		let parent_id = match nodes[id].parent() {
+			Some(parent_id) => parent_id,
+			None => break,
+		};
+
+		match nodes.get(parent_id) {
+			Some(parent) if parent.child_position(id).is_some() => id = parent_id,
+			_ => break,
+		}
+	}
+}
+
+/// Recomputes the stale subtree lengths of the given subtree,
+/// and returns its length.
+///
+/// Only the stale nodes are visited.
+///
+/// Computes in **O(k)** time, where `k` is the number of stale nodes.
+pub fn refresh_subtree_len(nodes: &mut Slots<Node>, id: usize) -> usize {
+	let count = match &nodes[id] {
+		Node::Internal(node) if node.is_stale() => node.item_count(),
+		node => return node.subtree_len(),
+	};
+
+	let mut len = count;
+	for position in 0..=count {
+		let child_id = nodes[id].child_id(position).unwrap();
+		len += refresh_subtree_len(nodes, child_id)
+	}
+
+	if let Node::Internal(node) = &mut nodes[id] {
+		node.set_subtree_len(len)
+	}
+
+	len
+}
+
+/// Recomputes the subtree length of the given node from its children.
+///
+/// Returns `false` if the node is internal and its length did not change.
+fn update_subtree_len(nodes: &mut Slots<Node>, id: usize) -> bool {
+	let len = match &nodes[id] {
+		Node::Internal(node) => {
+			let children: usize = (0..=node.item_count())
+				.filter_map(|p| node.child_id(p))
+				.map(|child_id| nodes[child_id].subtree_len())
+				.sum();
+			children + node.item_count()
+		}
+		Node::Leaf(_) => return true,
+	};
+
+	match &mut nodes[id] {
+		Node::Internal(node) if node.subtree_len() != len => {
+			node.set_subtree_len(len);
+			true
+		}
+		_ => false,
+	}
+}
+
+/// B-Tree, or subtree detached from its parent.
+#[derive(Clone, Copy)]
+pub struct Tree {
+	/// Root node id.
+	pub root: usize,
+
+	/// Height of the tree, `0` if the root is a leaf.
+	pub height: usize,
+}
+
+impl Tree {
+	/// Returns the tree rooted at the given node.
+	///
+	/// Computes in **O(log n)** time.
+	#[inline]
+	pub fn new(nodes: &Slots<Node>, root: usize) -> Self {
+		let mut height = 0;
+		let mut id = root;
+		while let Some(child_id) = nodes[id].child_id(0) {
+			height += 1;
+			id = child_id
+		}
+
+		Self { root, height }
+	}
+
+	/// Returns the tree rooted at the given node, detaching it from its parent
+	/// and updating its subtree length.
+	///
+	/// A node without items is removed, and replaced by its only child if any.
+	fn detach(nodes: &mut Slots<Node>, root: usize, height: usize) -> Option<Self> {
+		nodes[root].set_parent(None);
+		if nodes[root].item_count() > 0 {
+			update_subtree_len(nodes, root);
+			Some(Self { root, height })
+		} else {
+			let child_id = nodes.remove(root).child_id(0)?;
+			nodes[child_id].set_parent(None);
+			Some(Self {
+				root: child_id,
+				height: height - 1,
+			})
+		}
+	}
+
+	/// Returns the tree after an insertion that may have split its root.
+	#[inline]
+	fn grow(self, new_root: Option<usize>) -> Self {
+		match new_root {
+			Some(root) => Self {
+				root,
+				height: self.height + 1,
+			},
+			None => self,
+		}
+	}
+}
+
+/// Merges the right node into the left node,
+/// with the given separator item between them.
+///
+/// Both nodes must have the same height, and fit in a single node.
+fn merge(nodes: &mut Slots<Node>, left_id: usize, separator: Index, right_id: usize) {
+	let right = nodes.remove(right_id);
+	nodes[left_id].append(separator, right);
+	adopt_children(nodes, left_id);
+	update_subtree_len(nodes, left_id);
+}
+
+/// Rotates items through the separator item between two nodes of the same height,
+/// until both nodes hold at least [`MIN_ITEMS`] items.
+///
+/// Both nodes must hold too many items to be merged, separator included.
+fn balance(nodes: &mut Slots<Node>, left_id: usize, separator: &mut Index, right_id: usize) {
+	while nodes[left_id].item_count() < MIN_ITEMS {
+		let (child_id, item) = nodes[right_id].pop_front();
+		nodes[left_id].push_back(std::mem::replace(separator, item), child_id);
+		if let Some(child_id) = child_id {
+			nodes[child_id].set_parent(Some(left_id))
+		}
+	}
+
+	while nodes[right_id].item_count() < MIN_ITEMS {
+		let (item, child_id) = nodes[left_id].pop_back();
+		nodes[right_id].push_front(child_id, std::mem::replace(separator, item));
+		if let Some(child_id) = child_id {
+			nodes[child_id].set_parent(Some(right_id))
+		}
+	}
+
+	update_subtree_len(nodes, left_id);
+	update_subtree_len(nodes, right_id);
+}
+
+/// Inserts an item before the first item of the given tree,
+/// or after its last item if `back` is `true`.
+fn push_edge(nodes: &mut Slots<Node>, tree: Tree, item: Index, back: bool) -> Tree {
+	let mut id = tree.root;
+	let offset = loop {
+		let offset = if back { nodes[id].item_count() } else { 0 };
+		match nodes[id].child_id(offset) {
+			Some(child_id) => id = child_id,
+			None => break offset,
+		}
+	};
+
+	let tree = tree.grow(insert_item(nodes, id, offset, item, None));
+	update_path_lens(nodes, id);
+	tree
+}
+
+/// Joins two trees with the given separator item between them.
+///
+/// The items of `left` must be lower than the separator,
+/// itself lower than the items of `right`.
+/// Both trees are consumed, and the returned tree holds all the items.
+///
+/// Computes in **O(|h1 - h2| + 1)** time, where `h1` and `h2` are the heights
+/// of the trees, or **O(h)** if one of them is empty, without comparing any item.
+pub fn join(
+	nodes: &mut Slots<Node>,
+	left: Option<Tree>,
+	mut separator: Index,
+	right: Option<Tree>,
+) -> Tree {
+	let (left, right) = match (left, right) {
+		(Some(left), Some(right)) => (left, right),
+		(Some(left), None) => return push_edge(nodes, left, separator, true),
+		(None, Some(right)) => return push_edge(nodes, right, separator, false),
+		(None, None) => {
+			return Tree {
+				root: nodes.insert(Node::Leaf(leaf::Metadata::new(None, &[separator]))),
+				height: 0,
+			}
+		}
+	};
+
+	if left.height == right.height {
+		let count = nodes[left.root].item_count() + 1 + nodes[right.root].item_count();
+		if count <= nodes[left.root].max_item_count() {
+			merge(nodes, left.root, separator, right.root);
+			return left;
+		}
+
+		balance(nodes, left.root, &mut separator, right.root);
+		let mut root = internal::Metadata::new(None);
+		root.set_first_child_id(left.root);
+		root.push_right(separator, right.root);
+		let root_id = nodes.insert(Node::Internal(root));
+		adopt_children(nodes, root_id);
+		update_subtree_len(nodes, root_id);
+		Tree {
+			root: root_id,
+			height: left.height + 1,
+		}
+	} else if left.height > right.height {
+		// Attach `right` on the right edge of `left`.
+		let mut parent_id = left.root;
+		for _ in (right.height + 1)..left.height {
+			parent_id = nodes[parent_id]
+				.child_id(nodes[parent_id].item_count())
+				.unwrap()
+		}
+
+		let offset = nodes[parent_id].item_count();
+		let child_id = nodes[parent_id].child_id(offset).unwrap();
+		let count = nodes[child_id].item_count() + 1 + nodes[right.root].item_count();
+		if count <= nodes[child_id].max_item_count() {
+			merge(nodes, child_id, separator, right.root);
+			update_path_lens(nodes, child_id);
+			left
+		} else {
+			balance(nodes, child_id, &mut separator, right.root);
+			let new_root = insert_item(nodes, parent_id, offset, separator, Some(right.root));
+			update_path_lens(nodes, right.root);
+			left.grow(new_root)
+		}
+	} else {
+		// Attach `left` on the left edge of `right`.
+		let mut parent_id = right.root;
+		for _ in (left.height + 1)..right.height {
+			parent_id = nodes[parent_id].child_id(0).unwrap()
+		}
+
+		let child_id = nodes[parent_id].child_id(0).unwrap();
+		let count = nodes[left.root].item_count() + 1 + nodes[child_id].item_count();
+		if count <= nodes[child_id].max_item_count() {
+			merge(nodes, left.root, separator, child_id);
+			nodes[parent_id].set_child_id(0, left.root);
+			nodes[left.root].set_parent(Some(parent_id));
+			update_path_lens(nodes, left.root);
+			right
+		} else {
+			balance(nodes, left.root, &mut separator, child_id);
+			nodes[parent_id].set_child_id(0, left.root);
+			nodes[left.root].set_parent(Some(parent_id));
+			let new_root = insert_item(nodes, parent_id, 0, separator, Some(child_id));
+			update_path_lens(nodes, left.root);
+			right.grow(new_root)
+		}
+	}
+}
+
+/// Splits the given tree around the item of the given rank.
+///
+/// Returns the tree of the items lower than this item, the item itself,
+/// and the tree of the greater items.
+/// The tree must hold more than `rank` items.
+///
+/// Only the nodes on the path to the item are split,
+/// and the detached subtrees are joined back on each side.
+///
+/// Computes in **O(log n)** time, without comparing any item.
+pub fn split(
+	nodes: &mut Slots<Node>,
+	tree: Tree,
+	mut rank: usize,
+) -> (Option<Tree>, Index, Option<Tree>) {
+	let id = tree.root;
+	let mut position = 0;
+	loop {
+		let len = nodes[id]
+			.child_id(position)
+			.map(|child_id| nodes[child_id].subtree_len())
+			.unwrap_or(0);
+		if rank < len {
+			break;
+		}
+
+		rank -= len;
+		if rank == 0 {
+			// The item is in this node.
+			let (item, right) = nodes[id].split_off(position);
+			let right_id = nodes.insert(right);
+			adopt_children(nodes, right_id);
+			let left = Tree::detach(nodes, id, tree.height);
+			let right = Tree::detach(nodes, right_id, tree.height);
+			return (left, item, right);
+		}
+
+		rank -= 1;
+		position += 1
+	}
+
+	// The item is in the child at `position`.
+	let child_id = nodes[id].child_id(position).unwrap();
+	let right = if position < nodes[id].item_count() {
+		let (separator, right) = nodes[id].split_off(position);
+		let right_id = nodes.insert(right);
+		adopt_children(nodes, right_id);
+		Some((separator, Tree::detach(nodes, right_id, tree.height)))
+	} else {
+		None
+	};
+
+	let left = if position > 0 {
+		let (separator, _) = nodes[id].pop_back();
+		Some((Tree::detach(nodes, id, tree.height), separator))
+	} else {
+		nodes.remove(id);
+		None
+	};
+
+	nodes[child_id].set_parent(None);
+	let child = Tree {
+		root: child_id,
+		height: tree.height - 1,
+	};
+	let (child_left, item, child_right) = split(nodes, child, rank);
+
+	let left = match left {
+		Some((left, separator)) => Some(join(nodes, left, separator, child_left)),
+		None => child_left,
+	};
+
+	let right = match right {
+		Some((separator, right)) => Some(join(nodes, child_right, separator, right)),
+		None => child_right,
+	};
+
+	(left, item, right)
+}
+
+/// Moves the given subtree from `source` to `target`.
+///
+/// Each item is replaced by the result of `f`, called on the items in key order.
+/// Returns the id of the subtree root in `target`, whose parent is set to `parent`.
+///
+/// Computes in **O(k)** time, where `k` is the number of items in the subtree.
+pub fn transfer<F>(
+	source: &mut Slots<Node>,
+	target: &mut Slots<Node>,
+	id: usize,
+	parent: Option<usize>,
+	f: &mut F,
+) -> usize
+where
+	F: FnMut(Index) -> Index,
+{
+	let mut node = source.remove(id);
+	node.set_parent(parent);
+	let new_id = target.insert(node);
+
+	let count = target[new_id].item_count();
+	for position in 0..=count {
+		if let Some(child_id) = target[new_id].child_id(position) {
+			let child_id = transfer(source, target, child_id, Some(new_id), f);
+			target[new_id].set_child_id(position, child_id)
+		}
+
+		if position < count {
+			let item = f(target[new_id].item(position).unwrap());
+			target[new_id].set_item(position, item)
+		}
+	}
+
+	new_id
+}
+
+/// Removes every node of the given subtree,
+/// calling `f` on each of its items in key order.
+///
+/// Computes in **O(k)** time, where `k` is the number of items in the subtree.
+pub fn remove_subtree<F>(nodes: &mut Slots<Node>, id: usize, f: &mut F)
+where
+	F: FnMut(Index),
+{
+	let node = nodes.remove(id);
+	for position in 0..=node.item_count() {
+		if let Some(child_id) = node.child_id(position) {
+			remove_subtree(nodes, child_id, f)
+		}
+
+		if let Some(item) = node.item(position) {
+			f(item)
+		}
+	}
+}
+
+/// Maximum number of items in a leaf.
+///
+/// `generic_btree` considers that a leaf overflows when it reaches
+/// its maximum capacity of `M + 1` items.
+const MAX_LEAF_ITEMS: usize = M;
+
+/// Maximum number of items in an internal node.
+///
+/// `generic_btree` considers that an internal node overflows when it reaches
+/// its maximum capacity of `M` items.
+const MAX_INTERNAL_ITEMS: usize = M - 1;
+
+/// Recomputes the subtree length of the given node and of all its ancestors.
+///
+/// Computes in **O(log n)** time.
+fn update_path_lens(nodes: &mut Slots<Node>, mut id: usize) {
+	loop {
+		update_subtree_len(nodes, id);
+		match nodes[id].parent() {
+			Some(parent_id) => id = parent_id,
+			None => break,
+		}
+	}
+}
+
+/// Makes the given node the parent of each of its children.
+fn adopt_children(nodes: &mut Slots<Node>, id: usize) {
+	for position in 0..=nodes[id].item_count() {
+		match nodes[id].child_id(position) {
+			Some(child_id) => nodes[child_id].set_parent(Some(id)),
+			None => break,
+		}
+	}
+}
+
+/// Inserts an item with its right child at the given offset in the given node.
+///
+/// A full node is split in two before the insertion,
+/// and the item separating both halves is inserted in the parent node the same way.
+/// Nodes are split with the same bounds as the insertions of `generic_btree`,
+/// so that both keep the same invariants:
+/// nodes never hold more than [`Node::max_item_count`] items,
+/// and non-root nodes never hold less than [`MIN_ITEMS`] items.
+/// Tests check these invariants with `validate`.
+///
+/// Returns the id of the new root node if the root has been split.
+/// The subtree length of the split nodes is updated,
+/// but the one of their ancestors must be updated by the caller.
+fn insert_item(
+	nodes: &mut Slots<Node>,
+	id: usize,
+	offset: usize,
+	item: Index,
+	child_id: Option<usize>,
+) -> Option<usize> {
+	let count = nodes[id].item_count();
+	if count < nodes[id].max_item_count() {
+		nodes[id].insert(offset, item, child_id);
+		if let Some(child_id) = child_id {
+			nodes[child_id].set_parent(Some(id))
+		}
+
+		return None;
+	}
+
+	let median = count / 2;
+	let (separator, right) = nodes[id].split_off(median);
+	let right_id = nodes.insert(right);
+	adopt_children(nodes, right_id);
+
+	let (target_id, offset) = if offset <= median {
+		(id, offset)
+	} else {
+		(right_id, offset - median - 1)
+	};
+	nodes[target_id].insert(offset, item, child_id);
+	if let Some(child_id) = child_id {
+		nodes[child_id].set_parent(Some(target_id))
+	}
+
+	update_subtree_len(nodes, id);
+	update_subtree_len(nodes, right_id);
+
+	match nodes[id].parent() {
+		Some(parent_id) => {
+			let position = nodes[parent_id].child_position(id).unwrap();
+			insert_item(nodes, parent_id, position, separator, Some(right_id))
+		}
+		None => {
+			let mut root = internal::Metadata::new(None);
+			root.set_first_child_id(id);
+			root.push_right(separator, right_id);
+			let root_id = nodes.insert(Node::Internal(root));
+			adopt_children(nodes, root_id);
+			update_subtree_len(nodes, root_id);
+			Some(root_id)
+		}
+	}
+}
+
+/// Checks the invariants of the B-Tree with the given root, and panics if one is broken.
+///
+/// Every node must point to its parent, and hold at most [`Node::max_item_count`] items.
+/// Nodes other than the root must hold at least [`MIN_ITEMS`] items.
+/// Internal nodes must store the length of their subtree,
+/// and all the leaves must be at the same depth.
+///
+/// Returns the number of nodes of the tree.
+#[cfg(test)]
+pub fn validate(nodes: &Slots<Node>, root: Option<usize>) -> usize {
+	fn visit(
+		nodes: &Slots<Node>,
+		id: usize,
+		parent: Option<usize>,
+		depth: usize,
+		leaf_depth: &mut Option<usize>,
+	) -> usize {
+		let node = &nodes[id];
+		let count = node.item_count();
+		assert_eq!(node.parent(), parent, "wrong parent for node {}", id);
+		assert!(count <= node.max_item_count(), "node {} overflows", id);
+		match parent {
+			Some(_) => assert!(count >= MIN_ITEMS, "node {} underflows", id),
+			None => assert!(count > 0, "empty root node"),
+		}
+
+		let mut len = count;
+		let mut node_count = 1;
+		match node {
+			Node::Leaf(_) => {
+				let leaf_depth = *leaf_depth.get_or_insert(depth);
+				assert_eq!(leaf_depth, depth, "unbalanced leaf {}", id)
+			}
+			Node::Internal(_) => {
+				for position in 0..=count {
+					let child_id = node.child_id(position).unwrap();
+					node_count += visit(nodes, child_id, Some(id), depth + 1, leaf_depth);
+					len += nodes[child_id].subtree_len()
+				}
+			}
+		}
+
+		assert_eq!(node.subtree_len(), len, "wrong subtree length {}", id);
+		node_count
+	}
+
+	match root {
+		Some(id) => visit(nodes, id, None, 0, &mut None),
+		None => 0,
+	}
+}
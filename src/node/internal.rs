@@ -10,6 +10,7 @@ pub use reference::Ref;
 pub use reference_mut::Mut;
 
 /// Node branch.
+#[derive(Clone)]
 pub struct Branch {
 	/// Item index.
 	item_index: Index,
@@ -36,6 +37,7 @@ impl Branch {
 }
 
 /// Internal node metadata.
+#[derive(Clone)]
 pub struct Metadata {
 	/// Parent node index.
 	parent: Option<usize>,
@@ -45,6 +47,195 @@ pub struct Metadata {
 
 	/// Branches.
 	branches: SmallVec<[Branch; M]>,
+
+	/// Number of items in the subtree rooted at this node.
+	len: usize,
+
+	/// Whether the subtree has been modified since `len` was computed.
+	stale: bool,
+}
+
+impl Metadata {
+	/// Creates a new internal node without items.
+	///
+	/// The first child must then be set with [`Self::set_first_child_id`].
+	#[inline]
+	pub fn new(parent: Option<usize>) -> Self {
+		Self {
+			parent,
+			first_child_id: 0,
+			branches: SmallVec::new(),
+			len: 0,
+			stale: false,
+		}
+	}
+
+	/// Returns the parent node id, if any.
+	#[inline]
+	pub fn parent(&self) -> Option<usize> {
+		self.parent
+	}
+
+	#[inline]
+	pub fn set_parent(&mut self, parent: Option<usize>) {
+		self.parent = parent
+	}
+
+	/// Returns the number of items stored in the node.
+	#[inline]
+	pub fn item_count(&self) -> usize {
+		self.branches.len()
+	}
+
+	/// Returns the item at the given offset.
+	#[inline]
+	pub fn item(&self, offset: usize) -> Option<Index> {
+		self.branches.get(offset).map(Branch::item_index)
+	}
+
+	/// Replaces the item at the given offset, keeping its right child.
+	#[inline]
+	pub fn set_item(&mut self, offset: usize, item: Index) {
+		self.branches[offset].item_index = item
+	}
+
+	/// Returns the id of the child at the given position.
+	///
+	/// The child at position `0` is the first child,
+	/// the child at position `i + 1` is the one on the right of the item at offset `i`.
+	#[inline]
+	pub fn child_id(&self, position: usize) -> Option<usize> {
+		match position {
+			0 => Some(self.first_child_id),
+			_ => self.branches.get(position - 1).map(|b| b.child_id),
+		}
+	}
+
+	#[inline]
+	pub fn set_first_child_id(&mut self, id: usize) {
+		self.first_child_id = id
+	}
+
+	/// Replaces the child at the given position.
+	#[inline]
+	pub fn set_child_id(&mut self, position: usize, id: usize) {
+		match position {
+			0 => self.first_child_id = id,
+			_ => self.branches[position - 1].child_id = id,
+		}
+	}
+
+	/// Returns the number of items in the subtree rooted at this node.
+	///
+	/// The length must not be stale.
+	#[inline]
+	pub fn subtree_len(&self) -> usize {
+		debug_assert!(!self.stale, "stale subtree length");
+		self.len
+	}
+
+	/// Sets the number of items in the subtree rooted at this node,
+	/// which is not stale anymore.
+	#[inline]
+	pub fn set_subtree_len(&mut self, len: usize) {
+		self.len = len;
+		self.stale = false
+	}
+
+	/// Checks if the subtree has been modified since its length was computed.
+	#[inline]
+	pub fn is_stale(&self) -> bool {
+		self.stale
+	}
+
+	/// Marks the subtree length as stale.
+	///
+	/// Returns `false` if it was already stale.
+	#[inline]
+	pub fn mark_stale(&mut self) -> bool {
+		!std::mem::replace(&mut self.stale, true)
+	}
+
+	/// Appends an item with its right child at the end of the node.
+	#[inline]
+	pub fn push_right(&mut self, item: Index, child_id: usize) {
+		self.branches.push(Branch::new(item, child_id))
+	}
+
+	/// Inserts an item with its right child at the given offset.
+	///
+	/// This does not rebalance the tree:
+	/// the caller must ensure that the node does not overflow.
+	#[inline]
+	pub fn insert(&mut self, offset: usize, item: Index, child_id: usize) {
+		self.branches.insert(offset, Branch::new(item, child_id))
+	}
+
+	/// Removes the last item with its right child.
+	#[inline]
+	pub fn pop_back(&mut self) -> (Index, usize) {
+		self.branches.pop().unwrap().into_pair()
+	}
+
+	/// Inserts an item with its left child at the front of the node.
+	#[inline]
+	pub fn push_front(&mut self, child_id: usize, item: Index) {
+		let first_child_id = std::mem::replace(&mut self.first_child_id, child_id);
+		self.branches.insert(0, Branch::new(item, first_child_id))
+	}
+
+	/// Removes the first child with the first item.
+	#[inline]
+	pub fn pop_front(&mut self) -> (usize, Index) {
+		let (item, first_child_id) = self.branches.remove(0).into_pair();
+		let child_id = std::mem::replace(&mut self.first_child_id, first_child_id);
+		(child_id, item)
+	}
+
+	/// Appends the given separator item and the content of the given node.
+	///
+	/// The first child of `other` becomes the right child of the separator.
+	/// The caller must ensure that the node does not overflow.
+	#[inline]
+	pub fn append(&mut self, separator: Index, other: Self) {
+		self.branches
+			.push(Branch::new(separator, other.first_child_id));
+		self.branches.extend(other.branches)
+	}
+
+	/// Splits the node around the item at the given offset.
+	///
+	/// Returns this item, and a new node with the same parent
+	/// holding the items that follow it and their children.
+	/// The right child of the item becomes the first child of the new node.
+	/// The subtree length of both nodes must then be recomputed.
+	#[inline]
+	pub fn split_off(&mut self, offset: usize) -> (Index, Self) {
+		let branches = self.branches.drain((offset + 1)..).collect();
+		let (item, first_child_id) = self.branches.pop().unwrap().into_pair();
+		let right = Self {
+			parent: self.parent,
+			first_child_id,
+			branches,
+			len: 0,
+			stale: false,
+		};
+
+		(item, right)
+	}
+
+	/// Returns the position of the given child in the node.
+	#[inline]
+	pub fn child_position(&self, id: usize) -> Option<usize> {
+		if self.first_child_id == id {
+			Some(0)
+		} else {
+			self.branches
+				.iter()
+				.position(|b| b.child_id == id)
+				.map(|i| i + 1)
+		}
+	}
 }
 
 impl Default for Metadata {
@@ -53,6 +244,8 @@ impl Default for Metadata {
 			parent: None,
 			first_child_id: 0,
 			branches: SmallVec::new(),
+			len: 0,
+			stale: false,
 		}
 	}
 }
@@ -71,7 +264,7 @@ impl<'a, K, V> generic_btree::node::buffer::Internal<crate::Mut<'a, K, V>> for M
 	}
 
 	fn child_id(&self, index: usize) -> Option<usize> {
-		self.branches.get(index).map(|b| b.child_id)
+		Metadata::child_id(self, index)
 	}
 
 	fn max_capacity(&self) -> usize {
@@ -61,7 +61,7 @@ impl<'r, 'a: 'r, K, V> generic_btree::node::InternalRef<crate::Ref<'a, K, V>> fo
 	}
 
 	fn child_id(&self, index: usize) -> Option<usize> {
-		self.meta.branches.get(index).map(|b| b.child_id)
+		self.meta.child_id(index)
 	}
 
 	fn max_capacity(&self) -> usize {
@@ -75,7 +75,7 @@ impl<'r, 'a: 'r, K, V> generic_btree::node::InternalRef<crate::Mut<'a, K, V>> fo
 	}
 
 	fn child_id(&self, index: usize) -> Option<usize> {
-		self.meta.branches.get(index).map(|b| b.child_id)
+		self.meta.child_id(index)
 	}
 
 	fn max_capacity(&self) -> usize {
@@ -9,11 +9,93 @@ mod reference_mut;
 pub use reference::Ref;
 pub use reference_mut::Mut;
 
+#[derive(Clone)]
 pub struct Metadata {
 	parent: Option<usize>,
 	items: SmallVec<[Index; M + 1]>,
 }
 
+impl Metadata {
+	/// Creates a new leaf with the given items.
+	#[inline]
+	pub fn new(parent: Option<usize>, items: &[Index]) -> Self {
+		Self {
+			parent,
+			items: SmallVec::from_slice(items),
+		}
+	}
+
+	/// Returns the parent node id, if any.
+	#[inline]
+	pub fn parent(&self) -> Option<usize> {
+		self.parent
+	}
+
+	#[inline]
+	pub fn set_parent(&mut self, parent: Option<usize>) {
+		self.parent = parent
+	}
+
+	/// Returns the number of items stored in the leaf.
+	#[inline]
+	pub fn item_count(&self) -> usize {
+		self.items.len()
+	}
+
+	/// Returns the item at the given offset.
+	#[inline]
+	pub fn item(&self, offset: usize) -> Option<Index> {
+		self.items.get(offset).cloned()
+	}
+
+	/// Replaces the item at the given offset.
+	#[inline]
+	pub fn set_item(&mut self, offset: usize, item: Index) {
+		self.items[offset] = item
+	}
+
+	/// Inserts an item at the given offset.
+	///
+	/// This does not rebalance the tree:
+	/// the caller must ensure that the leaf does not overflow.
+	#[inline]
+	pub fn insert(&mut self, offset: usize, item: Index) {
+		self.items.insert(offset, item)
+	}
+
+	/// Removes the last item.
+	#[inline]
+	pub fn pop_back(&mut self) -> Index {
+		self.items.pop().unwrap()
+	}
+
+	/// Removes the first item.
+	#[inline]
+	pub fn pop_front(&mut self) -> Index {
+		self.items.remove(0)
+	}
+
+	/// Appends the given separator item and the items of the given leaf.
+	///
+	/// The caller must ensure that the leaf does not overflow.
+	#[inline]
+	pub fn append(&mut self, separator: Index, other: Self) {
+		self.items.push(separator);
+		self.items.extend_from_slice(&other.items)
+	}
+
+	/// Splits the leaf around the item at the given offset.
+	///
+	/// Returns this item, and a new leaf with the same parent
+	/// holding the items that follow it.
+	#[inline]
+	pub fn split_off(&mut self, offset: usize) -> (Index, Self) {
+		let right = Self::new(self.parent, &self.items[(offset + 1)..]);
+		self.items.truncate(offset + 1);
+		(self.items.pop().unwrap(), right)
+	}
+}
+
 impl Default for Metadata {
 	fn default() -> Self {
 		Self {
@@ -1,18 +1,211 @@
-use super::{index, node, Inner, Item, Node};
-use generic_btree::Storage;
-use slab::Slab;
-use std::{borrow::Borrow, cmp::Ordering};
+use super::{index, node, slots::Slots, Index, Inner, Item, Node};
+use generic_btree::{Address, Storage};
+use std::{
+	borrow::Borrow,
+	cmp::Ordering,
+	ops::{Bound, RangeBounds},
+};
 
 pub struct Ref<'a, K, V> {
-	nodes: &'a Slab<Node>,
+	nodes: &'a Slots<Node>,
 	inner: &'a Inner<K, V>,
 	root: Option<usize>,
 }
 
 impl<'a, K, V> Ref<'a, K, V> {
-	pub(crate) fn new(nodes: &'a Slab<Node>, inner: &'a Inner<K, V>, root: Option<usize>) -> Self {
+	pub(crate) fn new(nodes: &'a Slots<Node>, inner: &'a Inner<K, V>, root: Option<usize>) -> Self {
 		Self { nodes, inner, root }
 	}
+
+	/// Finds the offset of the given key in the given node.
+	///
+	/// Returns `Err` with the offset of the first greater item if the key is
+	/// not in the node.
+	fn offset_in<Q: ?Sized>(&self, node: &Node, key: &Q) -> Result<usize, usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let mut offset = 0;
+		while let Some(index) = node.item(offset) {
+			match self.inner.items.get(index).unwrap().key.borrow().cmp(key) {
+				Ordering::Less => offset += 1,
+				Ordering::Equal => return Ok(offset),
+				Ordering::Greater => break,
+			}
+		}
+
+		Err(offset)
+	}
+
+	/// Returns the address of the first item greater than the given bound.
+	///
+	/// Computes in **O(log n)** time.
+	pub(crate) fn lower_bound<Q: ?Sized>(&self, bound: Bound<&Q>) -> Option<Address>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		match bound {
+			Bound::Included(key) => match self.address_of(key) {
+				Ok(address) => Some(address),
+				Err(address) => self.normalize(address),
+			},
+			Bound::Excluded(key) => match self.address_of(key) {
+				Ok(address) => self.next_item_address(address),
+				Err(address) => self.normalize(address),
+			},
+			Bound::Unbounded => self.first_item_address(),
+		}
+	}
+
+	/// Returns the address of the last item lower than the given bound.
+	///
+	/// Computes in **O(log n)** time.
+	pub(crate) fn upper_bound<Q: ?Sized>(&self, bound: Bound<&Q>) -> Option<Address>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		match bound {
+			Bound::Included(key) => match self.address_of(key) {
+				Ok(address) => Some(address),
+				Err(address) => self.previous_item_address(address),
+			},
+			Bound::Excluded(key) => match self.address_of(key) {
+				Ok(address) | Err(address) => self.previous_item_address(address),
+			},
+			Bound::Unbounded => self.last_item_address(),
+		}
+	}
+
+	/// Returns the addresses of the first and last items in the given range,
+	/// or `None` if the range is empty.
+	///
+	/// Computes in **O(log n)** time.
+	pub(crate) fn range_bounds<Q: ?Sized, R>(&self, range: &R) -> Option<(Address, Address)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		let front = self.lower_bound(range.start_bound())?;
+		let back = self.upper_bound(range.end_bound())?;
+
+		let front_key: &Q = self.item_at(front.into()).key.borrow();
+		let back_key: &Q = self.item_at(back.into()).key.borrow();
+		if front_key > back_key {
+			None
+		} else {
+			Some((front, back))
+		}
+	}
+
+	/// Returns the number of items in the subtree of the child at the given
+	/// position in the given node, or `0` if the node is a leaf.
+	#[inline]
+	fn child_len(&self, node: &Node, position: usize) -> usize {
+		node.child_id(position)
+			.map(|id| self.nodes[id].subtree_len())
+			.unwrap_or(0)
+	}
+
+	/// Finds the rank of the given key, that is the number of items with a
+	/// lower key.
+	///
+	/// Returns `Ok` if the key is in the tree, `Err` otherwise.
+	///
+	/// Computes in **O(log n)** time.
+	pub(crate) fn rank<Q: ?Sized>(&self, key: &Q) -> Result<usize, usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let mut id = match self.root {
+			Some(id) => id,
+			None => return Err(0),
+		};
+
+		let mut rank = 0;
+		loop {
+			let node = &self.nodes[id];
+			let (offset, found) = match self.offset_in(node, key) {
+				Ok(offset) => (offset, true),
+				Err(offset) => (offset, false),
+			};
+
+			rank += offset;
+			for position in 0..offset {
+				rank += self.child_len(node, position)
+			}
+
+			if found {
+				return Ok(rank + self.child_len(node, offset));
+			}
+
+			match node.child_id(offset) {
+				Some(child_id) => id = child_id,
+				None => return Err(rank),
+			}
+		}
+	}
+
+	/// Returns the number of items lower than the given range start bound.
+	///
+	/// Computes in **O(log n)** time.
+	pub(crate) fn count_before<Q: ?Sized>(&self, bound: Bound<&Q>) -> usize
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		match bound {
+			Bound::Included(key) => self.rank(key).unwrap_or_else(|rank| rank),
+			Bound::Excluded(key) => match self.rank(key) {
+				Ok(rank) => rank + 1,
+				Err(rank) => rank,
+			},
+			Bound::Unbounded => 0,
+		}
+	}
+
+	/// Returns the address of the item with the given rank.
+	///
+	/// Computes in **O(log n)** time.
+	pub(crate) fn address_of_rank(&self, mut rank: usize) -> Option<node::Address> {
+		let mut id = self.root?;
+		'nodes: loop {
+			let node = &self.nodes[id];
+			let count = node.item_count();
+			for position in 0..=count {
+				let len = self.child_len(node, position);
+				if rank < len {
+					id = node.child_id(position).unwrap();
+					continue 'nodes;
+				}
+
+				rank -= len;
+				if position < count {
+					if rank == 0 {
+						return Some(node::Address::new(id, position));
+					}
+
+					rank -= 1
+				}
+			}
+
+			return None;
+		}
+	}
+
+	/// Returns the index of the item at the given address.
+	pub(crate) fn index_at(&self, address: node::Address) -> Index {
+		self.nodes[address.id].item(address.offset).unwrap()
+	}
+
+	/// Returns the item at the given address.
+	pub(crate) fn item_at(&self, address: node::Address) -> &'a crate::item::Ordered<K, V> {
+		self.inner.items.get(self.index_at(address)).unwrap()
+	}
 }
 
 impl<'a, K, V> Storage for Ref<'a, K, V> {
@@ -1,17 +1,23 @@
-use super::{index, node, Index, Inner, Item, Node};
+use super::{index, item, node, slots::Slots, Index, Inner, Item, Node};
 use generic_btree::{Storage, StorageMut};
-use slab::Slab;
 use std::{borrow::Borrow, cmp::Ordering};
 
+/// Mutable B-Tree reference.
+///
+/// Internal nodes store the length of their subtree.
+/// Every node modified through this reference marks its subtree length,
+/// and the one of its ancestors, as stale.
+/// The stale lengths are recomputed when the reference is dropped,
+/// by visiting only the stale nodes from the root.
 pub struct Mut<'a, K, V> {
-	nodes: &'a mut Slab<Node>,
+	nodes: &'a mut Slots<Node>,
 	inner: &'a mut Inner<K, V>,
 	root: &'a mut Option<usize>,
 }
 
 impl<'a, K, V> Mut<'a, K, V> {
 	pub(crate) fn new(
-		nodes: &'a mut Slab<Node>,
+		nodes: &'a mut Slots<Node>,
 		inner: &'a mut Inner<K, V>,
 		root: &'a mut Option<usize>,
 	) -> Self {
@@ -19,6 +25,14 @@ impl<'a, K, V> Mut<'a, K, V> {
 	}
 }
 
+impl<'a, K, V> Drop for Mut<'a, K, V> {
+	fn drop(&mut self) {
+		if let Some(root) = *self.root {
+			node::refresh_subtree_len(self.nodes, root);
+		}
+	}
+}
+
 impl<'a, K, V> Storage for Mut<'a, K, V> {
 	type ItemRef<'r>
 	where
@@ -89,7 +103,13 @@ unsafe impl<'a, K, V> StorageMut for Mut<'a, K, V> {
 			generic_btree::node::Buffer::Internal(internal) => Node::Internal(internal),
 		};
 
-		self.nodes.insert(node)
+		let id = self.nodes.insert(node);
+		if let Node::Internal(node) = &mut self.nodes[id] {
+			// The node is attached later to a parent, which is marked then.
+			node.mark_stale();
+		}
+
+		id
 	}
 
 	fn release_node(&mut self, id: usize) -> generic_btree::node::Buffer<Self> {
@@ -101,6 +121,10 @@ unsafe impl<'a, K, V> StorageMut for Mut<'a, K, V> {
 
 	/// Returns the node with the given id, if any.
 	fn node_mut(&mut self, id: usize) -> Option<generic_btree::node::Mut<'_, Self>> {
+		if self.nodes.contains(id) {
+			node::mark_stale(self.nodes, id)
+		}
+
 		let inner = &mut self.inner;
 		self.nodes.get_mut(id).map(move |node| match node {
 			Node::Leaf(leaf) => generic_btree::node::Mut::leaf(node::leaf::Mut::new(leaf, inner)),
@@ -113,7 +137,20 @@ unsafe impl<'a, K, V> StorageMut for Mut<'a, K, V> {
 
 impl<'a, K, V> generic_btree::Insert<Item<K, V>> for Mut<'a, K, V> {
 	fn allocate_item(&mut self, item: Item<K, V>) -> Index {
-		self.inner.insert(item)
+		let capacity = self.inner.capacity();
+		let index = self.inner.insert(item);
+		if self.inner.capacity() != capacity {
+			// The items have been reallocated, grow the nodes accordingly.
+			node::reserve(self.nodes, self.inner.capacity())
+		}
+
+		index
+	}
+}
+
+impl<'a, K, V> generic_btree::Insert<item::Unique<K, V>> for Mut<'a, K, V> {
+	fn allocate_item(&mut self, item: item::Unique<K, V>) -> Index {
+		generic_btree::Insert::allocate_item(self, item.0)
 	}
 }
 
@@ -141,3 +178,34 @@ where
 		index_ref.item().key.partial_cmp(&other.key)
 	}
 }
+
+impl<'a, K, V> generic_btree::KeyPartialOrd<item::Unique<K, V>> for crate::Mut<'a, K, V>
+where
+	K: PartialOrd,
+{
+	fn key_partial_cmp<'r>(
+		index_ref: &index::Ref<'r, K, V>,
+		other: &item::Unique<K, V>,
+	) -> Option<Ordering>
+	where
+		Self: 'r,
+	{
+		index_ref.item().key.partial_cmp(&other.0.key)
+	}
+}
+
+impl<'a, K, V> generic_btree::KeyPartialOrd<index::ItemKey> for crate::Mut<'a, K, V>
+where
+	K: Ord,
+{
+	fn key_partial_cmp<'r>(
+		index_ref: &index::Ref<'r, K, V>,
+		other: &index::ItemKey,
+	) -> Option<Ordering>
+	where
+		Self: 'r,
+	{
+		let other_ref = index_ref.with_index(other.0);
+		Some(index_ref.item().key.cmp(&other_ref.item().key))
+	}
+}
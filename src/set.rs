@@ -0,0 +1,489 @@
+use crate::{IndexMap, IntoKeys, Keys};
+use std::{borrow::Borrow, collections::TryReserveError, fmt};
+
+/// B-Tree set preserving the insertion order of its elements.
+///
+/// The elements are the keys of an underlying [`IndexMap<T, ()>`](IndexMap).
+pub struct IndexSet<T> {
+	map: IndexMap<T, ()>,
+}
+
+impl<T> IndexSet<T> {
+	/// Creates a new empty set.
+	#[inline]
+	pub fn new() -> Self {
+		Self {
+			map: IndexMap::new(),
+		}
+	}
+
+	/// Wraps the given map, whose keys are the values of the set.
+	#[inline]
+	pub(crate) fn from_map(map: IndexMap<T, ()>) -> Self {
+		Self { map }
+	}
+
+	/// Unwraps the underlying map.
+	#[inline]
+	pub(crate) fn into_map(self) -> IndexMap<T, ()> {
+		self.map
+	}
+
+	/// Creates a new empty set with the given capacity.
+	#[inline]
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self {
+			map: IndexMap::with_capacity(capacity),
+		}
+	}
+
+	/// Returns the number of values the set can hold without reallocating.
+	///
+	/// See [`IndexMap::capacity`] for the guarantees of the capacity.
+	#[inline]
+	pub fn capacity(&self) -> usize {
+		self.map.capacity()
+	}
+
+	/// Reserves capacity for at least `additional` more values.
+	///
+	/// ## Panics
+	///
+	/// Panics if the new capacity overflows `usize`.
+	#[inline]
+	pub fn reserve(&mut self, additional: usize) {
+		self.map.reserve(additional)
+	}
+
+	/// Reserves capacity for exactly `additional` more values.
+	///
+	/// See [`IndexMap::reserve_exact`].
+	///
+	/// ## Panics
+	///
+	/// Panics if the new capacity overflows `usize`.
+	#[inline]
+	pub fn reserve_exact(&mut self, additional: usize) {
+		self.map.reserve_exact(additional)
+	}
+
+	/// Tries to reserve capacity for at least `additional` more values.
+	///
+	/// See [`IndexMap::try_reserve`].
+	#[inline]
+	pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+		self.map.try_reserve(additional)
+	}
+
+	/// Shrinks the capacity of the set as much as possible.
+	///
+	/// See [`IndexMap::shrink_to`] for the limits of shrinking.
+	#[inline]
+	pub fn shrink_to_fit(&mut self) {
+		self.map.shrink_to_fit()
+	}
+
+	/// Shrinks the capacity of the set with a lower bound.
+	///
+	/// See [`IndexMap::shrink_to`] for the limits of shrinking.
+	#[inline]
+	pub fn shrink_to(&mut self, min_capacity: usize) {
+		self.map.shrink_to(min_capacity)
+	}
+
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// Get the index and value equal to the given value.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn get_full<Q: ?Sized>(&self, value: &Q) -> Option<(usize, &T)>
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map
+			.get_full(value)
+			.map(|(index, value, _)| (index, value))
+	}
+
+	/// Get the value equal to the given value.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn get<Q: ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.get_full(value).map(|(_, value)| value)
+	}
+
+	/// Get the index of the given value.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn get_index_of<Q: ?Sized>(&self, value: &Q) -> Option<usize>
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.get_index_of(value)
+	}
+
+	/// Get by index.
+	///
+	/// Computes in **O(1)** time (average).
+	#[inline]
+	pub fn get_index(&self, index: usize) -> Option<&T> {
+		self.map.get_index(index).map(|(value, _)| value)
+	}
+
+	/// Checks if the set contains the given value.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.contains_key(value)
+	}
+
+	/// Inserts a value in the set and returns its index,
+	/// and `true` if the value was not already in the set.
+	///
+	/// If the value is already present, the set is left unchanged.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn insert_full(&mut self, value: T) -> (usize, bool)
+	where
+		T: Ord,
+	{
+		let (index, existing) = self.map.insert_full(value, ());
+		(index, existing.is_none())
+	}
+
+	/// Inserts a value in the set.
+	///
+	/// Returns `true` if the value was not already in the set.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn insert(&mut self, value: T) -> bool
+	where
+		T: Ord,
+	{
+		self.map.insert(value, ()).is_none()
+	}
+
+	/// Remove value.
+	///
+	/// This is an alias to [`Self::swap_remove`],
+	/// meaning that this function *disturbs the order of the set*.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn remove<Q: ?Sized>(&mut self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.swap_remove(value)
+	}
+
+	/// Remove value.
+	///
+	/// The last value of the set takes the place of the removed one.
+	///
+	/// Computes in **O(log n)** time (average).
+	#[inline]
+	pub fn swap_remove<Q: ?Sized>(&mut self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.swap_remove(value).is_some()
+	}
+
+	/// Remove value, preserving the order of the remaining values.
+	///
+	/// Computes in **O(n)** time (average).
+	#[inline]
+	pub fn shift_remove<Q: ?Sized>(&mut self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.shift_remove(value).is_some()
+	}
+
+	/// Returns an iterator over the values of the set.
+	///
+	/// Values are iterated by order of insertion in the set.
+	#[inline]
+	pub fn iter(&self) -> Iter<T> {
+		Iter {
+			keys: self.map.keys(),
+		}
+	}
+}
+
+impl<T> Default for IndexSet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Clone> Clone for IndexSet<T> {
+	/// Clones the set, preserving the index of each value.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	fn clone(&self) -> Self {
+		Self {
+			map: self.map.clone(),
+		}
+	}
+
+	/// Overwrites `self` with a clone of `source`,
+	/// reusing the allocations of `self`.
+	#[inline]
+	fn clone_from(&mut self, source: &Self) {
+		self.map.clone_from(&source.map)
+	}
+}
+
+impl<T, U> PartialEq<IndexSet<U>> for IndexSet<T>
+where
+	T: PartialEq<U>,
+{
+	/// Checks that both sets contain the same values, regardless of their index.
+	///
+	/// Computes in **O(n)** time.
+	#[inline]
+	fn eq(&self, other: &IndexSet<U>) -> bool {
+		self.map == other.map
+	}
+}
+
+impl<T: Eq> Eq for IndexSet<T> {}
+
+impl<T: Ord> FromIterator<T> for IndexSet<T> {
+	/// Builds a set from the given values.
+	///
+	/// Duplicate values keep the index of their first occurrence.
+	///
+	/// Computes in **O(n log n)** time.
+	#[inline]
+	fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+		Self {
+			map: iter.into_iter().map(|value| (value, ())).collect(),
+		}
+	}
+}
+
+impl<T: Ord> Extend<T> for IndexSet<T> {
+	/// Inserts the given values, following the semantics of [`IndexSet::insert`].
+	#[inline]
+	fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+		self.map.extend(iter.into_iter().map(|value| (value, ())))
+	}
+}
+
+impl<'a, T: 'a + Ord + Copy> Extend<&'a T> for IndexSet<T> {
+	/// Inserts copies of the given values,
+	/// following the semantics of [`IndexSet::insert`].
+	#[inline]
+	fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+		self.extend(iter.into_iter().copied())
+	}
+}
+
+impl<T: fmt::Debug> fmt::Debug for IndexSet<T> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.iter()).finish()
+	}
+}
+
+impl<'a, T> IntoIterator for &'a IndexSet<T> {
+	type Item = &'a T;
+	type IntoIter = Iter<'a, T>;
+
+	#[inline]
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl<T> IntoIterator for IndexSet<T> {
+	type Item = T;
+	type IntoIter = IntoIter<T>;
+
+	#[inline]
+	fn into_iter(self) -> Self::IntoIter {
+		IntoIter {
+			keys: self.map.into_keys(),
+		}
+	}
+}
+
+pub struct Iter<'a, T> {
+	keys: Keys<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+	type Item = &'a T;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.keys.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.keys.next()
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.keys.next_back()
+	}
+}
+
+impl<'a, T> std::iter::FusedIterator for Iter<'a, T> {}
+impl<'a, T> std::iter::ExactSizeIterator for Iter<'a, T> {}
+
+pub struct IntoIter<T> {
+	keys: IntoKeys<T, ()>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+	type Item = T;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.keys.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.keys.next()
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.keys.next_back()
+	}
+}
+
+impl<T> std::iter::FusedIterator for IntoIter<T> {}
+impl<T> std::iter::ExactSizeIterator for IntoIter<T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn insert() {
+		let insert = [0, 4, 2, 12, 8, 7, 11, 5];
+		let mut set = IndexSet::new();
+
+		for (i, elt) in insert.into_iter().enumerate() {
+			assert_eq!(set.insert_full(elt), (i, true));
+			assert!(set.contains(&elt));
+			assert_eq!(set.get_index_of(&elt), Some(i));
+			assert_eq!(set.get_index(i), Some(&elt));
+		}
+
+		assert_eq!(set.insert_full(2), (2, false));
+		assert!(!set.insert(12));
+		assert_eq!(set.len(), insert.len());
+		assert!(!set.contains(&1));
+		assert!(set.iter().eq(insert.iter()));
+	}
+
+	#[test]
+	fn remove() {
+		let mut set = IndexSet::new();
+		for elt in [3, 1, 4, 5, 9, 2, 6] {
+			set.insert(elt);
+		}
+
+		assert!(set.swap_remove(&1));
+		assert!(!set.swap_remove(&1));
+		assert!(set.iter().eq([3, 6, 4, 5, 9, 2].iter()));
+
+		assert!(set.shift_remove(&4));
+		assert!(set.into_iter().eq([3, 6, 5, 9, 2]));
+	}
+
+	#[test]
+	fn from_iter_and_extend() {
+		let mut set: IndexSet<_> = [5, 3, 5, 8, 3, 1].into_iter().collect();
+		assert!(set.iter().eq([5, 3, 8, 1].iter()));
+		assert_eq!(set.get_index_of(&8), Some(2));
+
+		set.extend([2, 8, 9]);
+		set.extend(&[1, 0]);
+		assert!(set.iter().eq([5, 3, 8, 1, 2, 9, 0].iter()));
+
+		let empty: IndexSet<i32> = std::iter::empty().collect();
+		assert!(empty.is_empty());
+	}
+
+	#[test]
+	fn clone_and_eq() {
+		let set: IndexSet<_> = (0..50).map(|i| i * 7 % 50).collect();
+		let clone = set.clone();
+		assert!(clone.iter().eq(set.iter()));
+		assert_eq!(clone, set);
+
+		// Equality ignores the index of each value.
+		let reversed: IndexSet<_> = set.iter().rev().copied().collect();
+		assert_eq!(reversed, set);
+
+		let mut other: IndexSet<_> = (0..10).collect();
+		assert_ne!(other, set);
+		other.clone_from(&set);
+		assert!(other.iter().eq(set.iter()));
+	}
+
+	#[test]
+	fn reserve() {
+		let mut set: IndexSet<usize> = IndexSet::new();
+		set.reserve(100);
+		assert!(set.capacity() >= 100);
+		set.extend(0..100);
+		assert!(set.capacity() >= 100);
+
+		assert!(set.try_reserve(usize::MAX).is_err());
+		set.shrink_to_fit();
+		assert!(set.capacity() >= 100);
+	}
+}
@@ -0,0 +1,330 @@
+use crate::capacity_overflow;
+use std::{
+	collections::TryReserveError,
+	marker::PhantomData,
+	ops::{Index, IndexMut},
+	ptr::NonNull,
+};
+
+#[derive(Clone)]
+enum Slot<T> {
+	/// Free slot, with the index of the next free slot.
+	Vacant(usize),
+
+	/// Slot holding a value.
+	Occupied(T),
+}
+
+/// Values addressed by stable indexes.
+///
+/// The index of a value does not change until it is removed.
+/// The slot of a removed value is reused by the next insertion.
+///
+/// Both the items and the B-Tree nodes are stored here rather than in a `slab::Slab`,
+/// because the map needs what a slab does not offer:
+/// fallible reservations ([`Self::try_reserve`]) and reservations counted
+/// in values rather than in slots, so that the capacity of the map holds
+/// whatever the free slots are,
+/// conversions keeping every index and free slot in place ([`Self::try_map`]),
+/// and mutable references to several values at once ([`SlotsMut`]).
+#[derive(Clone)]
+pub struct Slots<T> {
+	/// Slots.
+	slots: Vec<Slot<T>>,
+
+	/// Number of occupied slots.
+	len: usize,
+
+	/// Index of the first free slot, or `slots.len()` if all the slots are occupied.
+	next: usize,
+}
+
+impl<T> Slots<T> {
+	#[inline]
+	pub fn new() -> Self {
+		Self {
+			slots: Vec::new(),
+			len: 0,
+			next: 0,
+		}
+	}
+
+	#[inline]
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self {
+			slots: Vec::with_capacity(capacity),
+			len: 0,
+			next: 0,
+		}
+	}
+
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns the number of values the storage can hold without reallocating.
+	#[inline]
+	pub fn capacity(&self) -> usize {
+		self.slots.capacity()
+	}
+
+	/// Returns the number of slots to allocate
+	/// for `additional` more values to fit without reallocating,
+	/// or `None` if the number of values overflows `usize`.
+	#[inline]
+	fn missing(&self, additional: usize) -> Option<usize> {
+		let needed = self.len.checked_add(additional)?;
+		Some(needed.saturating_sub(self.slots.len()))
+	}
+
+	/// Reserves capacity for at least `additional` more values.
+	///
+	/// ## Panics
+	///
+	/// Panics if the new capacity overflows `usize`.
+	#[inline]
+	pub fn reserve(&mut self, additional: usize) {
+		let missing = self.missing(additional).expect("capacity overflow");
+		self.slots.reserve(missing)
+	}
+
+	/// Reserves capacity for exactly `additional` more values.
+	///
+	/// ## Panics
+	///
+	/// Panics if the new capacity overflows `usize`.
+	#[inline]
+	pub fn reserve_exact(&mut self, additional: usize) {
+		let missing = self.missing(additional).expect("capacity overflow");
+		self.slots.reserve_exact(missing)
+	}
+
+	#[inline]
+	pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+		let missing = self.missing(additional).ok_or_else(capacity_overflow)?;
+		self.slots.try_reserve(missing)
+	}
+
+	#[inline]
+	pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+		let missing = self.missing(additional).ok_or_else(capacity_overflow)?;
+		self.slots.try_reserve_exact(missing)
+	}
+
+	/// Shrinks the capacity as much as possible.
+	///
+	/// Free slots between values are kept, so that indexes are preserved:
+	/// the capacity stays at least one past the index of the last occupied slot,
+	/// whatever the number of values.
+	#[inline]
+	pub fn shrink_to_fit(&mut self) {
+		self.slots.shrink_to_fit()
+	}
+
+	/// Removes all the values, keeping the allocated capacity.
+	#[inline]
+	pub fn clear(&mut self) {
+		self.slots.clear();
+		self.len = 0;
+		self.next = 0
+	}
+
+	/// Inserts a value in a free slot, and returns its index.
+	#[inline]
+	pub fn insert(&mut self, value: T) -> usize {
+		let index = self.next;
+		if index == self.slots.len() {
+			self.slots.push(Slot::Occupied(value));
+			self.next = index + 1
+		} else {
+			match std::mem::replace(&mut self.slots[index], Slot::Occupied(value)) {
+				Slot::Vacant(next) => self.next = next,
+				Slot::Occupied(_) => unreachable!(),
+			}
+		}
+
+		self.len += 1;
+		index
+	}
+
+	/// Removes the value at the given index, freeing its slot.
+	///
+	/// ## Panics
+	///
+	/// Panics if there is no value at the given index.
+	#[inline]
+	pub fn remove(&mut self, index: usize) -> T {
+		match self.slots.get_mut(index) {
+			Some(slot @ Slot::Occupied(_)) => {
+				match std::mem::replace(slot, Slot::Vacant(self.next)) {
+					Slot::Occupied(value) => {
+						self.next = index;
+						self.len -= 1;
+						value
+					}
+					Slot::Vacant(_) => unreachable!(),
+				}
+			}
+			_ => panic!("no value at index {}", index),
+		}
+	}
+
+	#[inline]
+	pub fn contains(&self, index: usize) -> bool {
+		matches!(self.slots.get(index), Some(Slot::Occupied(_)))
+	}
+
+	#[inline]
+	pub fn get(&self, index: usize) -> Option<&T> {
+		match self.slots.get(index) {
+			Some(Slot::Occupied(value)) => Some(value),
+			_ => None,
+		}
+	}
+
+	#[inline]
+	pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+		match self.slots.get_mut(index) {
+			Some(Slot::Occupied(value)) => Some(value),
+			_ => None,
+		}
+	}
+
+	/// Converts the values with the given fallible function, keeping their indexes.
+	///
+	/// The function is called on each value in the order of `indexes`,
+	/// which must list every occupied slot exactly once.
+	/// Free slots are kept as they are, so the next insertions fill the same slots.
+	///
+	/// ## Panics
+	///
+	/// Panics if `indexes` does not list every value exactly once.
+	pub fn try_map<U, E, I, F>(self, indexes: I, mut f: F) -> Result<Slots<U>, E>
+	where
+		I: IntoIterator<Item = usize>,
+		F: FnMut(T) -> Result<U, E>,
+	{
+		let mut old = self.slots;
+		let mut slots: Vec<Slot<U>> = Vec::with_capacity(old.capacity());
+		slots.extend(old.iter().map(|slot| match slot {
+			Slot::Vacant(next) => Slot::Vacant(*next),
+			Slot::Occupied(_) => Slot::Vacant(usize::MAX),
+		}));
+
+		let mut len = 0;
+		for index in indexes {
+			match std::mem::replace(&mut old[index], Slot::Vacant(usize::MAX)) {
+				Slot::Occupied(value) => slots[index] = Slot::Occupied(f(value)?),
+				Slot::Vacant(_) => panic!("no value at index {}", index),
+			}
+
+			len += 1
+		}
+
+		assert_eq!(len, self.len, "some values are not converted");
+		Ok(Slots {
+			slots,
+			len,
+			next: self.next,
+		})
+	}
+
+	/// Returns an iterator over the values, by increasing index.
+	#[cfg(test)]
+	#[inline]
+	pub fn iter(&self) -> impl Iterator<Item = &T> {
+		self.slots.iter().filter_map(|slot| match slot {
+			Slot::Occupied(value) => Some(value),
+			Slot::Vacant(_) => None,
+		})
+	}
+}
+
+impl<T> Default for Slots<T> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> Index<usize> for Slots<T> {
+	type Output = T;
+
+	/// Returns the value at the given index.
+	///
+	/// ## Panics
+	///
+	/// Panics if there is no value at the given index.
+	#[inline]
+	fn index(&self, index: usize) -> &T {
+		self.get(index)
+			.unwrap_or_else(|| panic!("no value at index {}", index))
+	}
+}
+
+impl<T> IndexMut<usize> for Slots<T> {
+	/// Returns the value at the given index.
+	///
+	/// ## Panics
+	///
+	/// Panics if there is no value at the given index.
+	#[inline]
+	fn index_mut(&mut self, index: usize) -> &mut T {
+		self.get_mut(index)
+			.unwrap_or_else(|| panic!("no value at index {}", index))
+	}
+}
+
+/// Exclusive borrow of a [`Slots`],
+/// handing out mutable references to distinct values for its whole lifetime.
+///
+/// Every reference is derived from a single pointer to the slots,
+/// taken when the storage is borrowed.
+/// Handing out a value does not borrow the other slots,
+/// so the references already handed out stay valid.
+pub struct SlotsMut<'a, T> {
+	/// First slot.
+	ptr: NonNull<Slot<T>>,
+
+	/// Number of slots.
+	len: usize,
+
+	marker: PhantomData<&'a mut [Slot<T>]>,
+}
+
+impl<'a, T> SlotsMut<'a, T> {
+	#[inline]
+	pub fn new(slots: &'a mut Slots<T>) -> Self {
+		let slots = slots.slots.as_mut_slice();
+		Self {
+			len: slots.len(),
+			ptr: NonNull::new(slots.as_mut_ptr()).unwrap(),
+			marker: PhantomData,
+		}
+	}
+
+	/// Returns a mutable reference to the value at the given index, valid for `'a`.
+	///
+	/// ## Panics
+	///
+	/// Panics if there is no value at the given index.
+	///
+	/// ## Safety
+	///
+	/// Each value must be requested at most once.
+	#[inline]
+	pub unsafe fn get(&mut self, index: usize) -> &'a mut T {
+		assert!(index < self.len, "no value at index {}", index);
+		// SAFETY: the index is in bounds, the slots are exclusively borrowed for `'a`,
+		// and the caller ensures that the values handed out are distinct.
+		match &mut *self.ptr.as_ptr().add(index) {
+			Slot::Occupied(value) => value,
+			Slot::Vacant(_) => panic!("no value at index {}", index),
+		}
+	}
+}
+
+// SAFETY: `SlotsMut` behaves like `&'a mut [T]`.
+unsafe impl<'a, T: Send> Send for SlotsMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for SlotsMut<'a, T> {}
@@ -0,0 +1,264 @@
+use crate::{item, Index, Inner, ItemsMut, Ref};
+use generic_btree::{Address, Storage};
+
+/// Indexes of the items of a B-Tree between two addresses (included), in key order.
+///
+/// The B-Tree is walked with the `generic_btree` traversal.
+pub(crate) struct Indexes<'a, K, V> {
+	btree: Ref<'a, K, V>,
+
+	/// Address of the next item to yield from the front.
+	front: Option<Address>,
+
+	/// Address of the next item to yield from the back.
+	back: Option<Address>,
+}
+
+impl<'a, K, V> Indexes<'a, K, V> {
+	/// Iterates over all the items of the B-Tree.
+	#[inline]
+	pub(crate) fn new(btree: Ref<'a, K, V>) -> Self {
+		Self {
+			front: btree.first_item_address(),
+			back: btree.last_item_address(),
+			btree,
+		}
+	}
+
+	/// Iterates over the items between the given addresses (included).
+	///
+	/// The first address must not be after the second one.
+	#[inline]
+	pub(crate) fn range(btree: Ref<'a, K, V>, bounds: Option<(Address, Address)>) -> Self {
+		Self {
+			btree,
+			front: bounds.map(|(front, _)| front),
+			back: bounds.map(|(_, back)| back),
+		}
+	}
+}
+
+impl<'a, K, V> Iterator for Indexes<'a, K, V> {
+	type Item = Index;
+
+	#[inline]
+	fn next(&mut self) -> Option<Index> {
+		let address = self.front?;
+		if self.front == self.back {
+			self.front = None;
+			self.back = None;
+		} else {
+			self.front = self.btree.next_item_address(address)
+		}
+
+		Some(self.btree.index_at(address.into()))
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for Indexes<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Index> {
+		let address = self.back?;
+		if self.front == self.back {
+			self.front = None;
+			self.back = None;
+		} else {
+			self.back = self.btree.previous_item_address(address)
+		}
+
+		Some(self.btree.index_at(address.into()))
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for Indexes<'a, K, V> {}
+
+/// Iterator over the bindings of a map, in key order.
+///
+/// Obtained with [`IndexMap::iter_sorted`](crate::IndexMap::iter_sorted).
+pub struct SortedIter<'a, K, V> {
+	inner: &'a Inner<K, V>,
+	indexes: Indexes<'a, K, V>,
+	len: usize,
+}
+
+impl<'a, K, V> SortedIter<'a, K, V> {
+	#[inline]
+	pub(crate) fn new(inner: &'a Inner<K, V>, indexes: Indexes<'a, K, V>) -> Self {
+		Self {
+			inner,
+			indexes,
+			len: inner.vec.len(),
+		}
+	}
+}
+
+impl<'a, K, V> Iterator for SortedIter<'a, K, V> {
+	type Item = (usize, &'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.len, Some(self.len))
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes.next().map(|index| {
+			self.len -= 1;
+			self.inner.items.get(index).unwrap().as_full()
+		})
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for SortedIter<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes.next_back().map(|index| {
+			self.len -= 1;
+			self.inner.items.get(index).unwrap().as_full()
+		})
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for SortedIter<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for SortedIter<'a, K, V> {}
+
+/// Iterator over the keys of a map, in key order.
+///
+/// Obtained with [`IndexMap::keys_sorted`](crate::IndexMap::keys_sorted).
+pub struct SortedKeys<'a, K, V> {
+	iter: SortedIter<'a, K, V>,
+}
+
+impl<'a, K, V> SortedKeys<'a, K, V> {
+	#[inline]
+	pub(crate) fn new(iter: SortedIter<'a, K, V>) -> Self {
+		Self { iter }
+	}
+}
+
+impl<'a, K, V> Iterator for SortedKeys<'a, K, V> {
+	type Item = &'a K;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next().map(|(_, key, _)| key)
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for SortedKeys<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.iter.next_back().map(|(_, key, _)| key)
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for SortedKeys<'a, K, V> {}
+impl<'a, K, V> std::iter::ExactSizeIterator for SortedKeys<'a, K, V> {}
+
+/// Iterator over a range of bindings of a map, in key order.
+///
+/// Obtained with [`IndexMap::range`](crate::IndexMap::range).
+pub struct Range<'a, K, V> {
+	inner: &'a Inner<K, V>,
+	indexes: Indexes<'a, K, V>,
+}
+
+impl<'a, K, V> Range<'a, K, V> {
+	#[inline]
+	pub(crate) fn new(inner: &'a Inner<K, V>, indexes: Indexes<'a, K, V>) -> Self {
+		Self { inner, indexes }
+	}
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+	type Item = (usize, &'a K, &'a V);
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.inner.items.get(index).unwrap().as_full())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.inner.items.get(index).unwrap().as_full())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for Range<'a, K, V> {}
+
+/// Iterator over a range of bindings of a map, in key order,
+/// with mutable references to the values.
+///
+/// Obtained with [`IndexMap::range_mut`](crate::IndexMap::range_mut).
+pub struct RangeMut<'a, K, V> {
+	items: ItemsMut<'a, K, V>,
+	indexes: Indexes<'a, K, V>,
+}
+
+impl<'a, K, V> RangeMut<'a, K, V> {
+	#[inline]
+	pub(crate) fn new(items: ItemsMut<'a, K, V>, indexes: Indexes<'a, K, V>) -> Self {
+		Self { items, indexes }
+	}
+
+	#[inline]
+	fn item_mut(&mut self, index: Index) -> &'a mut item::Ordered<K, V> {
+		// SAFETY: the B-Tree holds every item index once, and `indexes`
+		// walks it from both ends without crossing, so every item index
+		// is visited at most once.
+		unsafe { self.items.get(index) }
+	}
+}
+
+impl<'a, K, V> Iterator for RangeMut<'a, K, V> {
+	type Item = (usize, &'a K, &'a mut V);
+
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next()
+			.map(|index| self.item_mut(index).as_full_mut())
+	}
+
+	#[inline]
+	fn last(mut self) -> Option<Self::Item> {
+		self.next_back()
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for RangeMut<'a, K, V> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.indexes
+			.next_back()
+			.map(|index| self.item_mut(index).as_full_mut())
+	}
+}
+
+impl<'a, K, V> std::iter::FusedIterator for RangeMut<'a, K, V> {}